@@ -0,0 +1,248 @@
+// Copyright 2018 Commonwealth Labs, Inc.
+// This file is part of Edgeware.
+
+// Edgeware is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Edgeware is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Edgeware.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Simplified payment verification of Bitcoin transactions against a relayed
+//! header chain, used to back deposits of BTC with proofs.
+
+use rstd::prelude::*;
+use rstd::cmp;
+use primitives::{H256, U256};
+use sha2::{Digest, Sha256};
+
+/// Number of blocks between difficulty retargets
+pub const RETARGET_INTERVAL: u32 = 2016;
+/// Expected time span of a retarget interval, in seconds
+pub const TARGET_TIMESPAN: u32 = 14 * 24 * 60 * 60;
+
+const OP_RETURN: u8 = 0x6a;
+const OP_PUSHDATA1: u8 = 0x4c;
+
+/// Proof that a transaction is included in a relayed Bitcoin block. Hashes are
+/// in internal byte order, the reverse of how block explorers display them.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct TransactionProof {
+    /// Hash of the block including the transaction
+    pub block_hash: H256,
+    /// The transaction, serialized without witness data
+    pub transaction: Vec<u8>,
+    /// Sibling hashes from the transaction up to the merkle root
+    pub merkle_branch: Vec<H256>,
+    /// Position of the transaction within the block
+    pub index: u32,
+}
+
+/// A Bitcoin block header.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Header {
+    pub version: u32,
+    pub prev_hash: H256,
+    pub merkle_root: H256,
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+/// An output of a Bitcoin transaction.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Output {
+    pub value: u64,
+    pub script: Vec<u8>,
+}
+
+/// Double SHA-256 hash of the data, as used for Bitcoin block and transaction hashes
+pub fn sha256d(data: &[u8]) -> H256 {
+    H256::from_slice(&Sha256::digest(&Sha256::digest(data))[..])
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.data.len() < len {
+            return Err("Unexpected end of data");
+        }
+        let (read, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(read)
+    }
+
+    fn read_le(&mut self, len: usize) -> Result<u64, &'static str> {
+        Ok(self.read(len)?.iter().rev().fold(0u64, |v, b| (v << 8) | *b as u64))
+    }
+
+    fn read_varint(&mut self) -> Result<u64, &'static str> {
+        match self.read(1)?[0] {
+            0xfd => self.read_le(2),
+            0xfe => self.read_le(4),
+            0xff => self.read_le(8),
+            n => Ok(n as u64),
+        }
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], &'static str> {
+        let len = self.read_varint()?;
+        if len > self.data.len() as u64 {
+            return Err("Unexpected end of data");
+        }
+        self.read(len as usize)
+    }
+}
+
+/// Decode an 80-byte block header, returning its hash along with it
+pub fn decode_header(raw: &[u8]) -> Result<(H256, Header), &'static str> {
+    if raw.len() != 80 {
+        return Err("Invalid header length");
+    }
+    let mut reader = Reader { data: raw };
+    let header = Header {
+        version: reader.read_le(4)? as u32,
+        prev_hash: H256::from_slice(reader.read(32)?),
+        merkle_root: H256::from_slice(reader.read(32)?),
+        time: reader.read_le(4)? as u32,
+        bits: reader.read_le(4)? as u32,
+        nonce: reader.read_le(4)? as u32,
+    };
+    Ok((sha256d(raw), header))
+}
+
+/// Expand compact difficulty bits into a target
+pub fn target_from_bits(bits: u32) -> U256 {
+    let exponent = (bits >> 24) as usize;
+    let mantissa = bits & 0x007f_ffff;
+    if exponent <= 3 {
+        U256::from(mantissa >> (8 * (3 - exponent)))
+    } else if exponent > 32 {
+        U256::zero()
+    } else {
+        U256::from(mantissa) << (8 * (exponent - 3))
+    }
+}
+
+/// Compress a target into compact difficulty bits
+pub fn bits_from_target(target: U256) -> u32 {
+    let mut size = (target.bits() + 7) / 8;
+    let mut compact = if size <= 3 {
+        (target.low_u64() << (8 * (3 - size))) as u32
+    } else {
+        (target >> (8 * (size - 3))).low_u32()
+    };
+    if compact & 0x0080_0000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+    compact | (size as u32) << 24
+}
+
+/// Whether a block hash meets the target of its difficulty bits
+pub fn is_valid_pow(hash: &H256, bits: u32) -> bool {
+    U256::from_little_endian(&hash[..]) <= target_from_bits(bits)
+}
+
+/// Expected number of hashes needed to meet the target of difficulty bits
+pub fn work(bits: u32) -> U256 {
+    let target = target_from_bits(bits);
+    if target.is_zero() {
+        return U256::zero();
+    }
+    // 2^256 / (target + 1), without overflowing
+    (!target / (target + U256::one())) + U256::one()
+}
+
+/// Difficulty bits of the first block of a retarget interval, given the bits of
+/// the previous interval and the time it took, capped at the proof of work limit
+pub fn retarget(bits: u32, timespan: u32, pow_limit: u32) -> u32 {
+    let timespan = U256::from(cmp::max(cmp::min(timespan, TARGET_TIMESPAN * 4), TARGET_TIMESPAN / 4));
+    let target_timespan = U256::from(TARGET_TIMESPAN);
+    let target = target_from_bits(bits);
+    let limit = target_from_bits(pow_limit);
+
+    // target * timespan / target_timespan, split up so it only overflows when the
+    // result would exceed any limit anyway
+    let (scaled, overflow) = (target / target_timespan).overflowing_mul(timespan);
+    let (scaled, carry) = scaled.overflowing_add(target % target_timespan * timespan / target_timespan);
+    let new_target = if overflow || carry { limit } else { cmp::min(scaled, limit) };
+    bits_from_target(new_target)
+}
+
+/// Merkle root reached by hashing a transaction hash up its merkle branch
+pub fn merkle_root(txid: H256, branch: &[H256], index: u32) -> H256 {
+    let mut index = index;
+    branch.iter().fold(txid, |hash, sibling| {
+        let mut pair = Vec::with_capacity(64);
+        if index & 1 == 1 {
+            pair.extend_from_slice(&sibling[..]);
+            pair.extend_from_slice(&hash[..]);
+        } else {
+            pair.extend_from_slice(&hash[..]);
+            pair.extend_from_slice(&sibling[..]);
+        }
+        index >>= 1;
+        sha256d(&pair)
+    })
+}
+
+/// Decode the outputs of a transaction serialized without witness data
+pub fn decode_outputs(transaction: &[u8]) -> Result<Vec<Output>, &'static str> {
+    // 64 byte transactions can pass as inner nodes of a merkle tree
+    if transaction.len() == 64 {
+        return Err("Invalid 64 byte transaction");
+    }
+    let mut reader = Reader { data: transaction };
+    reader.read(4)?;
+
+    let inputs = reader.read_varint()?;
+    if inputs == 0 {
+        return Err("Witness serialization not supported");
+    }
+    for _ in 0..inputs {
+        reader.read(36)?;
+        reader.read_bytes()?;
+        reader.read(4)?;
+    }
+
+    let count = reader.read_varint()?;
+    let mut outputs = Vec::new();
+    for _ in 0..count {
+        let value = reader.read_le(8)?;
+        let script = reader.read_bytes()?.to_vec();
+        outputs.push(Output { value, script });
+    }
+    reader.read(4)?;
+    if !reader.data.is_empty() {
+        return Err("Invalid trailing transaction data");
+    }
+    Ok(outputs)
+}
+
+/// Data pushed by an `OP_RETURN` output script
+pub fn op_return_data(script: &[u8]) -> Option<&[u8]> {
+    if script.len() < 2 || script[0] != OP_RETURN {
+        return None;
+    }
+    let (len, data) = if script[1] == OP_PUSHDATA1 && script.len() > 2 {
+        (script[2] as usize, &script[3..])
+    } else if script[1] < OP_PUSHDATA1 {
+        (script[1] as usize, &script[2..])
+    } else {
+        return None;
+    };
+    if len == data.len() { Some(data) } else { None }
+}
@@ -36,38 +36,362 @@ extern crate srml_system as system;
 extern crate srml_session as session;
 
 use democracy::{Approved, VoteThreshold};
+use ethereum;
+use bitcoin;
+use mmr;
+use rlp;
 
 use rstd::prelude::*;
-use system::ensure_signed;
+use rstd::cmp;
+use primitives::{H160, H256, U256};
+use system::{ensure_signed, ensure_root, RawOrigin};
+use codec::{Compact, Decode, Encode};
 use runtime_support::{StorageValue, StorageMap};
 use runtime_support::dispatch::Result;
-use runtime_primitives::traits::{Zero, Hash};
+use runtime_primitives::Permill;
+use runtime_primitives::traits::{Zero, Hash, As};
 
 /// Record indices.
 pub type DepositIndex = u32;
 pub type WithdrawIndex = u32;
+/// Identifier of an eligible blockchain registered with the bridge.
+pub type ChainId = u32;
+/// Version of the layout of the bridge storage.
+pub type StorageVersionId = u32;
+
+/// Storage layout version of this runtime. Version 0 stored records as tuples
+/// keyed by transaction hash alone, version 1 stores typed records keyed by chain,
+/// version 2 indexes them in maps instead of lists and version 3 prunes final records.
+pub const STORAGE_VERSION: StorageVersionId = 3;
+
+/// Storage keys of the lists records were kept in before they were indexed
+const LEGACY_DEPOSITS_KEY: &[u8] = b"BridgeStorage Deposits";
+const LEGACY_WITHDRAWS_KEY: &[u8] = b"BridgeStorage Withdraws";
+/// Storage key prefix of deposit records keyed by transaction hash alone
+const LEGACY_DEPOSIT_OF_PREFIX: &[u8] = b"BridgeStorage DepositOf";
+/// Prefix of the message signing keys sign over an account id when registered to it
+pub const SIGNING_KEY_PROOF_PREFIX: &[u8] = b"Edgeware Bridge signing key for ";
+
+/// Monotonically increasing identifier of an installed authority set.
+pub type AuthoritySetId = u64;
+
+/// Address formats of eligible blockchains.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum AddressFormat {
+    /// 20-byte account addresses used by Ethereum and other EVM chains
+    Evm,
+    /// Output scripts used by Bitcoin
+    Bitcoin,
+}
+
+/// A recipient address on an eligible blockchain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ForeignAddress {
+    /// 20-byte EVM account address
+    Evm(H160),
+    /// Bitcoin output script paying the recipient
+    Bitcoin(Vec<u8>),
+}
+
+impl ForeignAddress {
+    /// The address format this address belongs to
+    pub fn format(&self) -> AddressFormat {
+        match *self {
+            ForeignAddress::Evm(_) => AddressFormat::Evm,
+            ForeignAddress::Bitcoin(_) => AddressFormat::Bitcoin,
+        }
+    }
+}
+
+/// Schemes authorities can sign withdraw approvals with.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum SignatureScheme {
+    /// ECDSA signature over the hash of the withdraw message
+    Raw,
+    /// ECDSA signature over the EIP-712 typed data hash of the withdraw
+    Eip712,
+}
+
+/// Sources the bridge authority set is drawn from.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum AuthoritySource {
+    /// The active session validators
+    Validators,
+    /// Members approved by governance
+    Governance,
+    /// Both the session validators and the governance-approved members
+    Union,
+}
+
+impl Default for AuthoritySource {
+    fn default() -> Self {
+        AuthoritySource::Validators
+    }
+}
+
+/// Source a member of an installed authority set came from.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum MemberSource {
+    /// An active session validator
+    Validator,
+    /// A governance-approved member
+    Governance,
+    /// Both an active session validator and a governance-approved member
+    ValidatorAndGovernance,
+}
+
+/// Proof that a deposit happened on an eligible blockchain, verified on-chain
+/// in place of authority signatures.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DepositProof {
+    /// Ethereum transaction and receipt included in a known block
+    EthereumReceipt(ethereum::ReceiptProof),
+    /// Bitcoin transaction included in a relayed block
+    BitcoinTransaction(bitcoin::TransactionProof),
+}
+
+/// Direction of a transfer across the bridge.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Direction {
+    /// Transfers from an eligible blockchain into Edgeware
+    Deposit,
+    /// Transfers from Edgeware out to an eligible blockchain
+    Withdraw,
+}
+
+/// Status of a deposit or withdraw record. New statuses are only ever appended
+/// so stored statuses keep their encoding.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Status {
+    /// Collecting signatures from bridge authorities
+    Pending,
+    /// Approved by the bridge authorities and queued until the rate limits allow execution
+    Approved,
+    /// Timed out before reaching approval
+    Expired,
+    /// Approved by the bridge authorities and processed
+    Executed,
+    /// Approved by the bridge authorities but could not be processed
+    Failed,
+    /// Withdrawn by its requester before reaching approval
+    Cancelled,
+    /// Found by governance to be fraudulent before it was processed
+    Rejected,
+}
+
+impl Status {
+    /// Move to the next status, if the transition is allowed
+    pub fn transition(self, next: Status) -> rstd::result::Result<Status, &'static str> {
+        let allowed = match (self, next) {
+            (Status::Pending, Status::Approved)
+            | (Status::Pending, Status::Expired)
+            | (Status::Pending, Status::Cancelled)
+            | (Status::Pending, Status::Rejected)
+            | (Status::Approved, Status::Executed)
+            | (Status::Approved, Status::Failed)
+            | (Status::Approved, Status::Rejected) => true,
+            _ => false,
+        };
+        if allowed { Ok(next) } else { Err("Invalid status transition") }
+    }
+
+    /// Whether no further transitions are allowed
+    pub fn is_final(self) -> bool {
+        match self {
+            Status::Pending | Status::Approved => false,
+            _ => true,
+        }
+    }
+}
+
+/// Deposit record as stored before chains were registered, with whether it was completed
+type LegacyDepositRecord<AccountId, Balance> = (DepositIndex, AccountId, Balance, Vec<AccountId>, bool);
+/// Withdraw record as stored before chains were registered, with whether it was completed
+type LegacyWithdrawRecord<AccountId, Balance> = (WithdrawIndex, AccountId, Balance, Vec<(AccountId, Vec<u8>)>, bool);
+
+/// A deposit from an eligible blockchain into Edgeware.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct DepositRecord<AccountId, Balance, BlockNumber> {
+    /// Position of the deposit among all deposits
+    pub index: DepositIndex,
+    /// Chain the deposit was made on
+    pub chain_id: ChainId,
+    /// Account credited with the deposit
+    pub target: AccountId,
+    /// Quantity deposited
+    pub quantity: Balance,
+    /// Authorities that signed the deposit
+    pub signers: Vec<AccountId>,
+    /// Progress of the deposit
+    pub status: Status,
+    /// Block the deposit was recorded at
+    pub created_at: BlockNumber,
+    /// Authority set that signs the deposit
+    pub authority_set_id: AuthoritySetId,
+}
+
+/// A withdraw from Edgeware out to an eligible blockchain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct WithdrawRecord<AccountId, Balance, BlockNumber> {
+    /// Position of the withdraw among all withdraws
+    pub index: WithdrawIndex,
+    /// Chain the funds are withdrawn to
+    pub chain_id: ChainId,
+    /// Account whose reserved funds are withdrawn
+    pub sender: AccountId,
+    /// Quantity withdrawn
+    pub quantity: Balance,
+    /// Part of the quantity paid to the signers, kept back by the foreign chain
+    pub fee: Balance,
+    /// Recipient of the funds on the foreign chain
+    pub recipient: ForeignAddress,
+    /// Authorities that signed the withdraw, with their signature scheme and signature
+    pub signatures: Vec<(AccountId, SignatureScheme, Vec<u8>)>,
+    /// Progress of the withdraw
+    pub status: Status,
+    /// Block the withdraw was requested at
+    pub created_at: BlockNumber,
+    /// Authority set that signs the withdraw
+    pub authority_set_id: AuthoritySetId,
+    /// Hash of the message authorities sign
+    pub message: H256,
+    /// EIP-712 typed data hash authorities sign with the EIP-712 scheme
+    pub typed_data_hash: H256,
+}
+
+/// Rule deciding when the signers of a record approve it.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ApprovalThreshold {
+    /// Approve once the signers hold at least `numerator / denominator` of the bonded
+    /// authority stake
+    StakeFraction(u32, u32),
+    /// Approve by a democracy vote threshold, counting the signers' stake as approving
+    /// and the rest of the bonded authority stake as against with full turnout
+    Vote(VoteThreshold),
+    /// Approve once `m` distinct authorities have signed
+    Count(u32),
+    /// Approve once the authority quorum of distinct authorities has signed,
+    /// regardless of their stake
+    AuthorityQuorum,
+}
+
+impl Default for ApprovalThreshold {
+    fn default() -> Self {
+        ApprovalThreshold::Vote(VoteThreshold::SuperMajorityApprove)
+    }
+}
+
+/// Rolling-window volume limits for a direction of a chain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RateLimit<Balance, BlockNumber> {
+    /// Length of the rolling window in blocks
+    pub window: BlockNumber,
+    /// Maximum volume executed within any window
+    pub max_volume: Balance,
+    /// Maximum quantity of a single transfer
+    pub max_single: Balance,
+}
+
+/// Registration info of an eligible blockchain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ChainInfo {
+    /// Human readable name of the chain
+    pub name: Vec<u8>,
+    /// Format of recipient addresses on the chain
+    pub format: AddressFormat,
+    /// Whether new deposit and withdraw requests are accepted for the chain
+    pub enabled: bool,
+    /// EIP-155 chain id of an EVM chain, which withdraw approvals are bound to
+    pub evm_chain_id: Option<u64>,
+}
 
 pub trait Trait: balances::Trait + session::Trait {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
-pub type LinkedProof = Vec<u8>;
-
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
         fn deposit_event() = default;
 
+        fn on_finalise(n: T::BlockNumber) {
+            // Migrate storage left behind by a runtime upgrade a chunk at a time,
+            // holding back queued and prunable records until it has been fully migrated
+            if Self::layout_version() < STORAGE_VERSION {
+                Self::migrate_storage();
+                if Self::pruning_held_from().is_none() {
+                    <PruningHeldFrom<T>>::put(n);
+                }
+                return;
+            }
+
+            // Prune final records whose retention period ends, catching up on the
+            // blocks held back by a migration first
+            Self::prune_held_records(n);
+            Self::prune_records(n);
+            Self::schedule_retained_records(n);
+            Self::prune_authority_sets(n);
+
+            // Execute queued approvals that fit in the rolling windows again
+            for chain_id in Self::chain_ids() {
+                Self::process_queue(chain_id, Direction::Deposit);
+                Self::process_queue(chain_id, Direction::Withdraw);
+            }
+
+            // Expose the root of the transfers executed up to this block
+            let root = mmr::root(Self::mmr_leaf_count(), &Self::mmr_peaks());
+            if root != Self::mmr_root() {
+                <MmrRoot<T>>::put(root);
+                Self::deposit_event(RawEvent::MmrRootUpdated(root, Self::mmr_leaf_count()));
+            }
+        }
+
         /// The deposit function should always succeed (in order) a deposit transaction
         /// on the eligible blockchain that has an established two-way peg with Edgeware.
         /// This function can be triggered by the depositor or any bridge authority that
         /// sees the transaction first.
-        pub fn deposit(origin, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
+        pub fn deposit(origin, chain_id: ChainId, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance, proof: Option<DepositProof>) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+            Self::ensure_chain_enabled(chain_id)?;
+            Self::ensure_not_paused(chain_id, Direction::Deposit)?;
+            Self::ensure_within_single_limit(chain_id, Direction::Deposit, quantity)?;
+            if let Some(ref proof) = proof {
+                Self::verify_deposit_proof(chain_id, &target, &transaction_hash, quantity, proof)?;
+            }
 
             // Match on deposit records by the respective transaction hash on the eligible blockchain
-            match <DepositOf<T>>::get(transaction_hash) {
-                Some(_) => { return Err("Deposit should not exist")},
+            // Pruned deposits leave a commitment behind so they cannot be deposited again
+            ensure!(Self::deposit_commitment((chain_id, transaction_hash)).is_none(), "Deposit should not exist");
+            match <DepositOf<T>>::get((chain_id, transaction_hash)) {
+                // A valid proof settles a pending record opened without one, so a record
+                // opened with the wrong target or quantity cannot hold the deposit back
+                Some(mut record) => {
+                    ensure!(proof.is_some() && record.status == Status::Pending, "Deposit should not exist");
+                    if record.target != target || record.quantity != quantity {
+                        // Signatures were collected for other parameters
+                        record.target = target.clone();
+                        record.quantity = quantity;
+                        record.signers = vec![];
+                    }
+                    record.status = record.status.transition(Status::Approved)?;
+                    <DepositOf<T>>::insert((chain_id, transaction_hash), record);
+                    Self::deposit_event(RawEvent::Deposit(chain_id, target, transaction_hash, quantity));
+                    Self::execute_or_enqueue_deposit(chain_id, transaction_hash, quantity)?;
+                },
                 None => {
                     // If sender is a bridge authority add them to the set of signers
                     let mut signers = vec![];
@@ -75,16 +399,30 @@ decl_module! {
                         signers.push(_sender);
                     }
 
-                    // Create new deposit record
-                    let mut deposits = <Deposits<T>>::get();
-                    deposits.push(transaction_hash);
-                    <Deposits<T>>::put(deposits);
-
                     // Insert deposit record and send event
                     let index = Self::deposit_count();
                     <DepositCount<T>>::mutate(|i| *i += 1);
-                    <DepositOf<T>>::insert(transaction_hash, (index, target.clone(), quantity, signers, false));
-                    Self::deposit_event(RawEvent::Deposit(target, transaction_hash, quantity));
+                    <DepositByIndex<T>>::insert(index, (chain_id, transaction_hash));
+                    let now = <system::Module<T>>::block_number();
+                    let status = if proof.is_some() { Status::Approved } else { Status::Pending };
+                    let record = DepositRecord {
+                        index,
+                        chain_id,
+                        target: target.clone(),
+                        quantity,
+                        signers,
+                        status,
+                        created_at: now,
+                        authority_set_id: Self::authority_set_id(),
+                    };
+                    Self::reserve_authority_set(record.authority_set_id);
+                    <DepositOf<T>>::insert((chain_id, transaction_hash), record);
+                    Self::deposit_event(RawEvent::Deposit(chain_id, target, transaction_hash, quantity));
+
+                    // Deposits backed by a valid proof need no authority signatures
+                    if proof.is_some() {
+                        Self::execute_or_enqueue_deposit(chain_id, transaction_hash, quantity)?;
+                    }
                 },
             }
 
@@ -93,43 +431,34 @@ decl_module! {
 
         /// The sign_deposit function should compile intentions (from sending tx) and
         /// check if a deposit proposal ever passes with each new valid signer.
-        pub fn sign_deposit(origin, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
+        pub fn sign_deposit(origin, chain_id: ChainId, target: T::AccountId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+            Self::ensure_not_paused(chain_id, Direction::Deposit)?;
 
-            match <DepositOf<T>>::get(transaction_hash) {
-                Some((inx, tgt, qty, signers, completed)) => {
+            match <DepositOf<T>>::get((chain_id, transaction_hash)) {
+                Some(mut record) => {
                     // Ensure all parameters match for safety
-                    ensure!(tgt == target.clone(), "Accounts do not match");
-                    ensure!(qty == quantity, "Quantities don't match");
-                    ensure!(!completed, "Transaction already completed");
-                    // Ensure sender is a bridge authority
-                    ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+                    ensure!(record.target == target, "Accounts do not match");
+                    ensure!(record.quantity == quantity, "Quantities don't match");
+                    Self::ensure_pending(record.status, record.chain_id, record.created_at)?;
+                    // Ensure sender is a bridge authority of the record
+                    ensure!(Self::can_sign(record.chain_id, record.authority_set_id, &_sender), "Invalid non-authority sender");
                     // Ensure senders can't sign twice
-                    ensure!(!signers.iter().any(|id| id == &_sender), "Invalid duplicate signings");
+                    ensure!(!record.signers.iter().any(|id| id == &_sender), "Invalid duplicate signings");
                     // Add record update with new signer
-                    let mut new_signers = signers.clone();
-                    new_signers.push(_sender.clone());
-
-                    // Check if we have reached enough signers for the deposit
-                    // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
-                    let stake_sum = new_signers.iter()
-                        .map(|s| <balances::Module<T>>::total_balance(s))
-                        .fold(Zero::zero(), |a, b| {
-                            let res = a + b;
-                            if res < a || res < b || res - b != a {
-                                panic!("Integer overflow in balance calculation")
-                            }
-                            res
-                        });
-
-                    // Check if we approve the proposal, if so, mark approved
-                    let total_issuance = <balances::Module<T>>::total_issuance();
-                    if VoteThreshold::SuperMajorityApprove.approved(stake_sum, total_issuance - stake_sum, total_issuance, total_issuance) {
-                        <balances::Module<T>>::increase_free_balance_creating(&tgt, qty);
-                        <DepositOf<T>>::insert(transaction_hash, (inx, tgt.clone(), qty, new_signers.clone(), true));
-                        Self::deposit_event(RawEvent::Approved(inx, tgt.clone(), qty, new_signers.clone()))
-                    } else {
-                        <DepositOf<T>>::insert(transaction_hash, (inx, tgt.clone(), qty, new_signers.clone(), false));
+                    record.signers.push(_sender.clone());
+
+                    // Check if we have reached enough bonded stake to approve the deposit, if so, mark approved
+                    // and execute it right away or queue it until the rate limits allow
+                    let approved = Self::is_approved(record.chain_id, Direction::Deposit, record.authority_set_id, &record.signers);
+                    if approved {
+                        record.status = record.status.transition(Status::Approved)?;
+                    }
+                    let (chain, qty) = (record.chain_id, record.quantity);
+                    <DepositOf<T>>::insert((chain_id, transaction_hash), record);
+                    if approved {
+                        Self::execute_or_enqueue_deposit(chain, transaction_hash, qty)?;
                     }
                 },
                 None => { return Err("Invalid transaction hash") },
@@ -143,34 +472,58 @@ decl_module! {
         /// eligible blockchain that has an established two-way peg with Edgeware. This
         /// function should only be called by a token holder interested in transferring
         /// native Edgeware tokens with Edgeware-compliant, non-native tokens like ERC20.
-        pub fn withdraw(origin, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
+        /// The quantity is reserved until the withdraw is approved.
+        pub fn withdraw(origin, chain_id: ChainId, recipient: ForeignAddress, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+            Self::ensure_chain_enabled(chain_id)?;
+            Self::ensure_not_paused(chain_id, Direction::Withdraw)?;
+            Self::ensure_within_single_limit(chain_id, Direction::Withdraw, quantity)?;
+            Self::ensure_valid_recipient(chain_id, &recipient)?;
 
             let mut nonce = Self::withdraw_nonce_of(_sender.clone());
-            let key = T::Hashing::hash_of(&(nonce, _sender.clone(), quantity));
+            let key = T::Hashing::hash_of(&(chain_id, nonce, _sender.clone(), recipient.clone(), quantity));
 
             match <WithdrawOf<T>>::get(key) {
                 Some(_) => { return Err("Withdraw already exists")},
                 None => {
-                    // If sender is a bridge authority add them to the set of signers
+                    // If sender is a bridge authority with a valid signature add them to the set of signers
+                    let index = Self::withdraw_count();
+                    let set_id = Self::authority_set_id();
+                    // The foreign chain releases the quantity less the fee paid to the signers
+                    let fee = Self::withdraw_fee() * quantity;
+                    let message = Self::encode_withdraw_message(chain_id, SignatureScheme::Raw, &recipient, quantity - fee, index, set_id)?;
+                    let typed_data_hash = Self::encode_withdraw_message(chain_id, SignatureScheme::Eip712, &recipient, quantity - fee, index, set_id)?;
                     let mut signers = vec![];
-                    if <Authorities<T>>::get().iter().any(|a| a == &_sender) {
-                        signers.push((_sender.clone(), signed_cross_chain_tx));
+                    if <Authorities<T>>::get().iter().any(|a| a == &_sender)
+                        && Self::verify_withdraw_signature(&_sender, &message, &signed_cross_chain_tx).is_ok()
+                    {
+                        signers.push((_sender.clone(), SignatureScheme::Raw, signed_cross_chain_tx));
                     }
 
-                    // Ensure sender has enough balance to withdraw from
-                    ensure!(<balances::Module<T>>::total_balance(&_sender) >= quantity, "Invalid balance for withdraw");
-
-                    // Create new withdraw record
-                    let mut withdraws = <Withdraws<T>>::get();
-                    withdraws.push(key);
-                    <Withdraws<T>>::put(withdraws);
+                    // Reserve the quantity so it cannot be spent or withdrawn twice while pending
+                    <balances::Module<T>>::reserve(&_sender, quantity).map_err(|_| "Invalid balance for withdraw")?;
 
                     // Insert withdraw record and send event
-                    let index = Self::withdraw_count();
                     <WithdrawCount<T>>::mutate(|i| *i += 1);
-                    <WithdrawOf<T>>::insert(key, (index, _sender.clone(), quantity, signers, false));
-                    Self::deposit_event(RawEvent::Withdraw(_sender.clone(), quantity));
+                    <WithdrawByIndex<T>>::insert(index, key);
+                    let now = <system::Module<T>>::block_number();
+                    Self::reserve_authority_set(set_id);
+                    <WithdrawOf<T>>::insert(key, WithdrawRecord {
+                        index,
+                        chain_id,
+                        sender: _sender.clone(),
+                        quantity,
+                        fee,
+                        recipient: recipient.clone(),
+                        signatures: signers,
+                        status: Status::Pending,
+                        created_at: now,
+                        authority_set_id: set_id,
+                        message,
+                        typed_data_hash,
+                    });
+                    Self::deposit_event(RawEvent::Withdraw(chain_id, _sender.clone(), recipient, quantity));
                 },
             }
 
@@ -180,39 +533,49 @@ decl_module! {
         }
 
         /// The sign_withdraw function should compile signatures (from send tx) and
-        /// check if a withdraw proposal ever passes with each new valid signer.
-        pub fn sign_withdraw(origin, target: T::AccountId, record_hash: T::Hash, quantity: T::Balance, signed_cross_chain_tx: Vec<u8>) -> Result {
+        /// check if a withdraw proposal ever passes with each new valid signer. The
+        /// signature must be a 65-byte ECDSA signature by the sender's registered
+        /// signing key over the withdraw message or its EIP-712 typed data hash.
+        pub fn sign_withdraw(origin, target: T::AccountId, record_hash: T::Hash, quantity: T::Balance, recipient: ForeignAddress, scheme: SignatureScheme, signed_cross_chain_tx: Vec<u8>) -> Result {
             let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
 
             match <WithdrawOf<T>>::get(record_hash) {
-                Some((inx, tgt, qty, signers, completed)) => {
+                Some(mut record) => {
+                    let chain = record.chain_id;
                     // Ensure all parameters match for safety
-                    ensure!(tgt == target.clone(), "Accounts do not match");
-                    ensure!(qty == quantity, "Quantities don't match");
-                    ensure!(!completed, "Transaction already completed");
-                    // Ensure sender is a bridge authority if record exists
-                    ensure!(Self::authorities().iter().any(|id| id == &_sender), "Invalid non-authority sender");
+                    ensure!(record.sender == target, "Accounts do not match");
+                    ensure!(record.quantity == quantity, "Quantities don't match");
+                    ensure!(record.recipient == recipient, "Recipients do not match");
+                    Self::ensure_not_paused(chain, Direction::Withdraw)?;
+                    Self::ensure_pending(record.status, chain, record.created_at)?;
+                    // Ensure sender is a bridge authority of the record
+                    ensure!(Self::can_sign(chain, record.authority_set_id, &_sender), "Invalid non-authority sender");
                     // Ensure senders can't sign twice
-                    ensure!(!signers.iter().any(|s| s.0 == _sender), "Invalid duplicate signings");
+                    ensure!(!record.signatures.iter().any(|s| s.0 == _sender), "Invalid duplicate signings");
+                    // Ensure the signature unlocks the funds on the foreign chain
+                    let digest = match scheme {
+                        SignatureScheme::Raw => record.message,
+                        SignatureScheme::Eip712 => record.typed_data_hash,
+                    };
+                    Self::verify_withdraw_signature(&_sender, &digest, &signed_cross_chain_tx)?;
                     // Add record update with new signer
-                    let mut new_signers = signers;
-                    new_signers.push((_sender.clone(), signed_cross_chain_tx));
-
-                    // Check if we have reached enough signers for the withdrawal
-                    // TODO: Ensure that checking balances is sufficient vs. finding explicit stake amounts
-                    let stake_sum = new_signers.iter()
-                        .map(|s| <balances::Module<T>>::total_balance(&s.0))
-                        .fold(Zero::zero(), |a,b| a + b);
-
-                    // Check if we approve the proposal
-                    let total_issuance = <balances::Module<T>>::total_issuance();
-                    if VoteThreshold::SuperMajorityApprove.approved(stake_sum, total_issuance - stake_sum, total_issuance, total_issuance) {
-                        <balances::Module<T>>::decrease_free_balance(&tgt, qty)?;
-                        // TODO: do we still mark completed on error? or store a "failed" tx?
-                        <WithdrawOf<T>>::insert(record_hash, (inx, tgt.clone(), qty, new_signers.clone(), true));
-                        Self::deposit_event(RawEvent::WithdrawSigned(_sender, target, record_hash, quantity));
+                    record.signatures.push((_sender.clone(), scheme, signed_cross_chain_tx));
+
+                    // Check if we have reached enough bonded stake to approve the withdrawal
+                    let signer_ids = record.signatures.iter().map(|s| s.0.clone()).collect::<Vec<_>>();
+                    if Self::is_approved(chain, Direction::Withdraw, record.authority_set_id, &signer_ids) {
+                        let qty = record.quantity;
+                        ensure!(<balances::Module<T>>::reserved_balance(&record.sender) >= qty, "Insufficient reserved balance for withdraw");
+                        record.status = record.status.transition(Status::Approved)?;
+                        <WithdrawOf<T>>::insert(record_hash, record);
+                        if Self::queued((chain, Direction::Withdraw)).is_empty() && Self::has_capacity(chain, Direction::Withdraw, qty) {
+                            Self::execute_withdraw(record_hash)?;
+                        } else {
+                            Self::enqueue(chain, Direction::Withdraw, record_hash);
+                        }
                     } else {
-                        <WithdrawOf<T>>::insert(record_hash, (inx, tgt.clone(), qty, new_signers.clone(), false));
+                        <WithdrawOf<T>>::insert(record_hash, record);
                     }
                 },
                 None => { return Err("Invalid record hash") },
@@ -220,78 +583,2045 @@ decl_module! {
 
             Ok(())
         }
-    }
-}
 
-impl<T: Trait> Module<T> {
-    pub fn withdraw_record_hash(index: usize) -> T::Hash {
-        return <Withdraws<T>>::get()[index];
-    }
-}
+        /// Register the Ethereum address of the secp256k1 key the sender signs
+        /// withdraws with. The proof is a 65-byte ECDSA signature by that key over
+        /// the signing key proof message of the sender, and a key can only be
+        /// registered to one account.
+        pub fn register_signing_key(origin, address: H160, proof: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(!address.is_zero(), "Invalid zero signing key");
+            ensure!(
+                Self::signing_key_owner(address).map_or(true, |owner| owner == _sender),
+                "Signing key already registered"
+            );
+            let message = Self::signing_key_proof_message(&_sender);
+            ensure!(ethereum::recover_address(&message, &proof)? == address, "Invalid signing key proof");
 
-impl<X, T> session::OnSessionChange<X> for Module<T>
-where
-    T: Trait,
-    T: session::Trait,
-{
-    fn on_session_change(_: X, _: bool) {
-        let next_authorities = <session::Module<T>>::validators()
-            .into_iter()
-            .collect::<Vec<T::AccountId>>();
+            if let Some(previous) = Self::signing_key(_sender.clone()) {
+                <SigningKeyOwner<T>>::remove(previous);
+            }
+            <SigningKeys<T>>::insert(_sender.clone(), address);
+            <SigningKeyOwner<T>>::insert(address, _sender.clone());
+            Self::deposit_event(RawEvent::SigningKeyRegistered(_sender, address));
+            Ok(())
+        }
 
-        // instant changes
-        let last_authorities = <Authorities<T>>::get();
-        if next_authorities != last_authorities {
-            <Authorities<T>>::put(next_authorities.clone());
-            Self::deposit_event(RawEvent::NewAuthorities(next_authorities));
+        /// Bond stake to back bridge authority duties. Bonded stake is reserved and
+        /// weighs the signatures of the account while it is a bridge authority.
+        pub fn bond(origin, value: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(!value.is_zero(), "Invalid zero bond");
+
+            <balances::Module<T>>::reserve(&_sender, value).map_err(|_| "Invalid balance for bond")?;
+            <Bonded<T>>::insert(_sender.clone(), Self::bonded(_sender.clone()) + value);
+            Self::deposit_event(RawEvent::StakeBonded(_sender, value));
+            Ok(())
         }
-    }
-}
 
+        /// Start unbonding stake. The stake stops counting towards signing weight right
+        /// away and can be withdrawn once the unbonding period has passed.
+        pub fn unbond(origin, value: T::Balance) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let bonded = Self::bonded(_sender.clone());
+            ensure!(!value.is_zero(), "Invalid zero unbond");
+            ensure!(bonded >= value, "Invalid unbond exceeding bonded stake");
+
+            let unlock_at = <system::Module<T>>::block_number() + Self::unbonding_period();
+            <Bonded<T>>::insert(_sender.clone(), bonded - value);
+            <Unbonding<T>>::mutate(_sender.clone(), |chunks| chunks.push((value, unlock_at)));
+            Self::deposit_event(RawEvent::StakeUnbonded(_sender, value, unlock_at));
+            Ok(())
+        }
 
-/// An event in this module.
-decl_event!(
-    pub enum Event<T> where <T as system::Trait>::Hash,
-                            <T as system::Trait>::AccountId,
-                            <T as balances::Trait>::Balance {
-        /// Deposit event for an account, an eligible blockchain transaction hash, and quantity
-        Deposit(AccountId, Hash, Balance),
-        /// Withdraw event for an account, and an amount
-        Withdraw(AccountId, Balance),
-        /// New authority set has been applied.
-        NewAuthorities(Vec<AccountId>),
-        /// Approved
-        Approved(u32, AccountId, Balance, Vec<AccountId>),
-        /// Withdrawl signed
-        WithdrawSigned(AccountId, AccountId, Hash, Balance),
-    }
-);
+        /// Release all unbonding stake whose unbonding period has passed.
+        pub fn withdraw_unbonded(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let now = <system::Module<T>>::block_number();
 
-decl_storage! {
-    trait Store for Module<T: Trait> as BridgeStorage {
-        /// Mapping from an eligible blockchain by Hash(name) to the list of block headers
-        /// TODO: V2 feature when we have stronger proofs of transfers
-        pub BlockHeaders get(block_headers): map T::Hash => Vec<T::Hash>;
+            let (unlocked, locked): (Vec<_>, Vec<_>) = Self::unbonding(_sender.clone())
+                .into_iter()
+                .partition(|&(_, unlock_at)| unlock_at <= now);
+            let value = unlocked.iter().fold(Zero::zero(), |a: T::Balance, c| a + c.0);
+            ensure!(!value.is_zero(), "No unbonded stake to withdraw");
 
-        /// The active set of bridge authorities who can sign off on requests
-        pub Authorities get(authorities) config(): Vec<T::AccountId>;
+            <balances::Module<T>>::unreserve(&_sender, value);
+            if locked.is_empty() {
+                <Unbonding<T>>::remove(_sender.clone());
+            } else {
+                <Unbonding<T>>::insert(_sender.clone(), locked);
+            }
+            Self::deposit_event(RawEvent::UnbondedWithdrawn(_sender, value));
+            Ok(())
+        }
+
+        /// Mark a pending deposit record whose chain timeout has passed as expired.
+        /// Anyone can expire a stale deposit.
+        pub fn expire_deposit(origin, chain_id: ChainId, transaction_hash: T::Hash) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+
+            match <DepositOf<T>>::get((chain_id, transaction_hash)) {
+                Some(mut record) => {
+                    ensure!(record.status == Status::Pending, "Transaction no longer pending");
+                    ensure!(Self::is_timed_out(record.chain_id, record.created_at), "Transaction has not timed out");
+
+                    record.status = record.status.transition(Status::Expired)?;
+                    let chain = record.chain_id;
+                    Self::release_authority_set(record.authority_set_id);
+                    <DepositOf<T>>::insert((chain_id, transaction_hash), record);
+                    Self::schedule_pruning(Direction::Deposit, chain, transaction_hash);
+                    Self::deposit_event(RawEvent::DepositExpired(chain, transaction_hash));
+                },
+                None => { return Err("Invalid transaction hash") },
+            }
+
+            Ok(())
+        }
+
+        /// Mark a pending withdraw record whose chain timeout has passed as expired
+        /// and return the reserved funds to the requester. Anyone can expire a stale
+        /// withdraw.
+        pub fn expire_withdraw(origin, record_hash: T::Hash) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+
+            match <WithdrawOf<T>>::get(record_hash) {
+                Some(mut record) => {
+                    ensure!(record.status == Status::Pending, "Transaction no longer pending");
+                    ensure!(Self::is_timed_out(record.chain_id, record.created_at), "Transaction has not timed out");
+
+                    record.status = record.status.transition(Status::Expired)?;
+                    <balances::Module<T>>::unreserve(&record.sender, record.quantity);
+                    let chain = record.chain_id;
+                    Self::release_authority_set(record.authority_set_id);
+                    <WithdrawOf<T>>::insert(record_hash, record);
+                    Self::schedule_pruning(Direction::Withdraw, chain, record_hash);
+                    Self::deposit_event(RawEvent::WithdrawExpired(chain, record_hash));
+                },
+                None => { return Err("Invalid record hash") },
+            }
+
+            Ok(())
+        }
+
+        /// Cancel a withdraw requested by the sender and return the reserved funds. A
+        /// withdraw can only be cancelled while its signers hold less than the cancel
+        /// threshold fraction of the stake needed for approval.
+        pub fn cancel_withdraw(origin, record_hash: T::Hash) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+
+            match <WithdrawOf<T>>::get(record_hash) {
+                Some(mut record) => {
+                    let chain = record.chain_id;
+                    ensure!(record.sender == _sender, "Invalid non-requester sender");
+                    ensure!(record.status == Status::Pending, "Transaction no longer pending");
+                    if !record.signatures.is_empty() {
+                        let signer_ids = record.signatures.iter().map(|s| s.0.clone()).collect::<Vec<_>>();
+                        ensure!(
+                            Self::is_below_threshold_fraction(chain, Direction::Withdraw, record.authority_set_id, &signer_ids, Self::cancel_threshold()),
+                            "Withdraw already signed by too much stake"
+                        );
+                    }
+
+                    record.status = record.status.transition(Status::Cancelled)?;
+                    <balances::Module<T>>::unreserve(&record.sender, record.quantity);
+                    Self::release_authority_set(record.authority_set_id);
+                    <WithdrawOf<T>>::insert(record_hash, record);
+                    Self::schedule_pruning(Direction::Withdraw, chain, record_hash);
+                    Self::deposit_event(RawEvent::WithdrawCancelled(chain, _sender, record_hash));
+                },
+                None => { return Err("Invalid record hash") },
+            }
+
+            Ok(())
+        }
+
+        /// Set the number of blocks final records are kept for before they are pruned.
+        /// Records are retained while it is zero, and scheduled for pruning a batch per
+        /// block once it is set again.
+        pub fn set_record_retention(origin, retention: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            <RecordRetention<T>>::put(retention);
+            Ok(())
+        }
+
+        /// Set the fraction of the approval stake below which withdraws can still be
+        /// cancelled by their requesters.
+        pub fn set_cancel_threshold(origin, cancel_threshold: Permill) -> Result {
+            ensure_root(origin)?;
+            <CancelThreshold<T>>::put(cancel_threshold);
+            Ok(())
+        }
+
+        /// Claim all signer rewards accrued by the sender.
+        pub fn claim_rewards(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            let reward = Self::rewards(_sender.clone());
+            ensure!(!reward.is_zero(), "No rewards to claim");
+
+            <Rewards<T>>::remove(_sender.clone());
+            <balances::Module<T>>::increase_free_balance_creating(&_sender, reward);
+            Self::deposit_event(RawEvent::RewardsClaimed(_sender, reward));
+            Ok(())
+        }
+
+        /// Set the fees taken from approved deposits and withdraws along with the
+        /// bonus paid out of each fee to the earliest signers of a record.
+        pub fn set_fees(origin, deposit_fee: Permill, withdraw_fee: Permill, bonus_signers: u32, signer_bonus: Permill) -> Result {
+            ensure_root(origin)?;
+
+            <DepositFee<T>>::put(deposit_fee);
+            <WithdrawFee<T>>::put(withdraw_fee);
+            <BonusSigners<T>>::put(bonus_signers);
+            <SignerBonus<T>>::put(signer_bonus);
+            Ok(())
+        }
+
+        /// Submit evidence of a bridge authority approving two different withdraws to
+        /// an EVM chain with the same nonce and authority set, either of which a
+        /// bridge contract the chain has been configured with would accept. Each
+        /// approval is given as its signature scheme, recipient, amount and signature
+        /// by the registered signing key of the authority. Each offence can only be
+        /// reported once.
+        pub fn report_conflicting_withdraw(
+            origin,
+            authority: T::AccountId,
+            chain_id: ChainId,
+            evm_chain_id: u64,
+            contract: H160,
+            nonce: WithdrawIndex,
+            set_id: AuthoritySetId,
+            first: (SignatureScheme, H160, U256, Vec<u8>),
+            second: (SignatureScheme, H160, U256, Vec<u8>)
+        ) -> Result {
+            let _sender = ensure_signed(origin)?;
+            Self::ensure_migrated()?;
+            ensure!(Self::is_bridge_domain((chain_id, evm_chain_id, contract)), "Unknown bridge contract");
+            ensure!((first.1, first.2) != (second.1, second.2), "Withdraws do not conflict");
+            let offence = T::Hashing::hash_of(&(authority.clone(), chain_id, evm_chain_id, contract, nonce, set_id));
+            ensure!(!Self::processed_evidence(offence), "Evidence already processed");
+
+            let key = Self::signing_key(authority.clone()).ok_or("No signing key registered")?;
+            for &(scheme, recipient, amount, ref signature) in [first, second].iter() {
+                let digest = Self::evm_withdraw_digest(evm_chain_id, &contract, scheme, &recipient, amount, nonce, set_id);
+                ensure!(ethereum::recover_address(&digest, signature)? == key, "Invalid evidence signature");
+            }
+
+            Self::slash_authority(&authority);
+            <ProcessedEvidence<T>>::insert(offence, true);
+            Ok(())
+        }
+
+        /// Slash every authority that signed a deposit that governance has confirmed
+        /// never happened on the eligible blockchain. A deposit that has not been
+        /// executed yet is rejected. Signers of pruned deposits are slashed from the
+        /// signers kept along with their commitment.
+        pub fn report_fraudulent_deposit(origin, chain_id: ChainId, transaction_hash: T::Hash) -> Result {
+            ensure_root(origin)?;
+            Self::ensure_migrated()?;
+            ensure!(!Self::deposit_reported((chain_id, transaction_hash)), "Deposit already reported");
+
+            if let Some((_, signers)) = Self::deposit_commitment((chain_id, transaction_hash)) {
+                for signer in signers.iter() {
+                    Self::slash_authority(signer);
+                }
+                <ReportedDeposits<T>>::insert((chain_id, transaction_hash), true);
+                return Ok(());
+            }
+
+            match <DepositOf<T>>::get((chain_id, transaction_hash)) {
+                Some(mut record) => {
+                    if let Ok(status) = record.status.transition(Status::Rejected) {
+                        record.status = status;
+                        Self::release_authority_set(record.authority_set_id);
+                        <DepositOf<T>>::insert((chain_id, transaction_hash), record.clone());
+                        <Queued<T>>::mutate((chain_id, Direction::Deposit), |queue| queue.retain(|h| h != &transaction_hash));
+                        Self::schedule_pruning(Direction::Deposit, chain_id, transaction_hash);
+                        Self::deposit_event(RawEvent::DepositRejected(chain_id, transaction_hash));
+                    }
+                    for signer in record.signers.iter() {
+                        Self::slash_authority(signer);
+                    }
+                    <ReportedDeposits<T>>::insert((chain_id, transaction_hash), true);
+                },
+                None => { return Err("Invalid transaction hash") },
+            }
+
+            Ok(())
+        }
+
+        /// Set the EIP-155 chain id of an EVM chain, which withdraw approvals for its
+        /// bridge contract are bound to.
+        pub fn set_evm_chain_id(origin, chain_id: ChainId, evm_chain_id: u64) -> Result {
+            ensure_root(origin)?;
+            let mut info = <Chains<T>>::get(chain_id).ok_or("Chain is not registered")?;
+            ensure!(info.format == AddressFormat::Evm, "Chain is not an EVM chain");
+            info.evm_chain_id = Some(evm_chain_id);
+            <Chains<T>>::insert(chain_id, info);
+            Self::record_bridge_domain(chain_id);
+            Ok(())
+        }
+
+        /// Set the bridge contract whose lock events back proof-verified deposits
+        /// from a chain.
+        pub fn set_bridge_contract(origin, chain_id: ChainId, contract: H160) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            <BridgeContract<T>>::insert(chain_id, contract);
+            Self::record_bridge_domain(chain_id);
+            Ok(())
+        }
+
+        /// Checkpoint the transactions and receipts roots of a block of a chain,
+        /// against which deposit proofs are verified.
+        pub fn set_block_roots(origin, chain_id: ChainId, block_hash: H256, transactions_root: H256, receipts_root: H256) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            <BlockRoots<T>>::insert((chain_id, block_hash), (transactions_root, receipts_root));
+            Ok(())
+        }
+
+        /// Import a trusted header of a chain with its total difficulty, from which
+        /// relayed headers are linked.
+        pub fn set_checkpoint_header(origin, chain_id: ChainId, header: Vec<u8>, total_difficulty: U256) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            let (hash, header) = ethereum::decode_header(&header)?;
+            ensure!(Self::header((chain_id, hash)).is_none(), "Header already imported");
+
+            Self::import_header(chain_id, hash, header, total_difficulty);
+            <HeaderFinalAt<T>>::insert((chain_id, hash), <system::Module<T>>::block_number());
+            Self::select_best_header(chain_id, hash);
+            Ok(())
+        }
+
+        /// Relay an RLP encoded header of a chain. Submitters must be bridge authorities
+        /// or have at least the relayer bond bonded. Proof of work is not verified, so
+        /// relayed headers only take part in fork choice once a quorum of authorities
+        /// has attested them.
+        pub fn submit_header(origin, chain_id: ChainId, header: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            Self::ensure_relayer(&_sender)?;
+
+            let (hash, header) = ethereum::decode_header(&header)?;
+            ensure!(Self::header((chain_id, hash)).is_none(), "Header already imported");
+            let (parent, parent_total_difficulty) = Self::header((chain_id, header.parent_hash))
+                .ok_or("Unknown parent header")?;
+            ensure!(header.number == parent.number + 1, "Invalid header number");
+            ensure!(header.timestamp > parent.timestamp, "Invalid header timestamp");
+            ensure!(ethereum::is_valid_difficulty(parent.difficulty, header.difficulty), "Invalid header difficulty");
+
+            let total_difficulty = parent_total_difficulty + header.difficulty;
+            Self::import_header(chain_id, hash, header, total_difficulty);
+            Ok(())
+        }
+
+        /// Attest a relayed header of a chain as part of the foreign chain. Once a quorum
+        /// of the current authorities has attested a header whose parent was attested,
+        /// it takes part in fork choice and backs deposit proofs after the challenge
+        /// period of the chain, unless it is challenged before then.
+        pub fn attest_header(origin, chain_id: ChainId, hash: H256) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(Self::authorities().iter().any(|a| a == &_sender), "Invalid non-authority sender");
+            let (header, _) = Self::header((chain_id, hash)).ok_or("Unknown header")?;
+            ensure!(Self::header_final_at((chain_id, hash)).is_none(), "Header already attested");
+            let parent_final_at = Self::header_final_at((chain_id, header.parent_hash)).ok_or("Parent header not attested")?;
+
+            let mut attestations = Self::header_attestations((chain_id, hash));
+            ensure!(!attestations.iter().any(|a| a == &_sender), "Invalid duplicate attestation");
+            attestations.push(_sender);
+            let attested = Self::signer_count(&Self::authorities(), &attestations) as u32 >= Self::quorum();
+            <HeaderAttestations<T>>::insert((chain_id, hash), attestations);
+
+            if attested {
+                // Descendants stay challengeable for at least as long as their ancestors
+                let now = <system::Module<T>>::block_number();
+                let final_at = cmp::max(now + Self::header_challenge_period(chain_id), parent_final_at);
+                <HeaderFinalAt<T>>::insert((chain_id, hash), final_at);
+                <UnfinalHeaders<T>>::mutate(chain_id, |headers| {
+                    headers.retain(|h| Self::header_final_at((chain_id, *h)).map_or(false, |at| at > now));
+                    if final_at > now {
+                        headers.push(hash);
+                    }
+                });
+                Self::deposit_event(RawEvent::HeaderAttested(chain_id, hash));
+                Self::select_best_header(chain_id, hash);
+            }
+            Ok(())
+        }
+
+        /// Challenge an attested header of a chain within its challenge period once
+        /// governance has found it invalid. The header and its attested descendants are
+        /// dropped and their attesters slashed.
+        pub fn challenge_header(origin, chain_id: ChainId, hash: H256) -> Result {
+            ensure_root(origin)?;
+            let (header, _) = Self::header((chain_id, hash)).ok_or("Unknown header")?;
+            let now = <system::Module<T>>::block_number();
+            ensure!(Self::header_final_at((chain_id, hash)).map_or(false, |at| at > now), "Header not challengeable");
+
+            // Headers are attested after their parents, so descendants follow the header
+            let mut rejected = vec![hash];
+            let mut remaining = vec![];
+            for h in Self::unfinal_headers(chain_id) {
+                if h == hash {
+                    continue;
+                }
+                let parent = Self::header((chain_id, h)).map(|(header, _)| header.parent_hash);
+                if parent.map_or(false, |p| rejected.contains(&p)) {
+                    rejected.push(h);
+                } else {
+                    remaining.push(h);
+                }
+            }
+            <UnfinalHeaders<T>>::insert(chain_id, remaining);
+
+            let best = Self::best_header(chain_id)
+                .and_then(|b| Self::header((chain_id, b)).map(|(header, _)| (b, header.number)));
+            for h in rejected.iter() {
+                for attester in <HeaderAttestations<T>>::take((chain_id, *h)) {
+                    Self::slash_authority(&attester);
+                }
+                <Headers<T>>::remove((chain_id, *h));
+                <HeaderFinalAt<T>>::remove((chain_id, *h));
+                Self::deposit_event(RawEvent::HeaderRejected(chain_id, *h));
+            }
+
+            // Fall back to the parent of the header if the canonical chain included it
+            if let Some((best_hash, best_number)) = best {
+                if rejected.contains(&best_hash) {
+                    Self::set_best_header(chain_id, header.parent_hash, header.number - 1, Some(best_number), |h| {
+                        let (header, _) = Self::header((chain_id, h))?;
+                        Self::header((chain_id, header.parent_hash)).map(|(parent, _)| (header.parent_hash, parent.number))
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        /// Set the number of blocks attested headers of a chain can be challenged for
+        /// before they back deposit proofs.
+        pub fn set_header_challenge_period(origin, chain_id: ChainId, period: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            <HeaderChallengePeriod<T>>::insert(chain_id, period);
+            Ok(())
+        }
+
+        /// Import a trusted Bitcoin header with its height, chain work and the time of
+        /// the first block of its retarget interval, from which relayed headers are
+        /// linked. `pow_limit` caps the target of the chain in compact form.
+        pub fn set_bitcoin_checkpoint(origin, chain_id: ChainId, header: Vec<u8>, height: u32, chain_work: U256, period_start: u32, pow_limit: u32) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            let (hash, header) = bitcoin::decode_header(&header)?;
+            ensure!(Self::bitcoin_header((chain_id, hash)).is_none(), "Header already imported");
+
+            <PowLimit<T>>::insert(chain_id, pow_limit);
+            Self::import_bitcoin_header(chain_id, hash, header, height, chain_work, period_start);
+            Ok(())
+        }
+
+        /// Relay an 80-byte Bitcoin header. Submitters must be bridge authorities or
+        /// have at least the relayer bond bonded.
+        pub fn submit_bitcoin_header(origin, chain_id: ChainId, header: Vec<u8>) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            Self::ensure_relayer(&_sender)?;
+
+            let (hash, header) = bitcoin::decode_header(&header)?;
+            ensure!(Self::bitcoin_header((chain_id, hash)).is_none(), "Header already imported");
+            let (parent, parent_height, parent_work, parent_period_start) = Self::bitcoin_header((chain_id, header.prev_hash))
+                .ok_or("Unknown parent header")?;
+
+            // Difficulty only changes on the first block of each retarget interval
+            let height = parent_height + 1;
+            let retargets = height % bitcoin::RETARGET_INTERVAL == 0;
+            let expected_bits = if retargets {
+                bitcoin::retarget(parent.bits, parent.time.saturating_sub(parent_period_start), Self::pow_limit(chain_id))
+            } else {
+                parent.bits
+            };
+            ensure!(header.bits == expected_bits, "Invalid header difficulty");
+            ensure!(bitcoin::is_valid_pow(&hash, header.bits), "Invalid proof of work");
+
+            let period_start = if retargets { header.time } else { parent_period_start };
+            let chain_work = parent_work + bitcoin::work(header.bits);
+            Self::import_bitcoin_header(chain_id, hash, header, height, chain_work, period_start);
+            Ok(())
+        }
+
+        /// Set the output script of the federation that receives Bitcoin deposits
+        pub fn set_federation_script(origin, chain_id: ChainId, script: Vec<u8>) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            <FederationScript<T>>::insert(chain_id, script);
+            Ok(())
+        }
+
+        /// Set the number of relayed headers needed on top of a header of a chain
+        /// before its roots back deposit proofs.
+        pub fn set_confirmation_depth(origin, chain_id: ChainId, depth: u64) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            ensure!(depth > 0, "Invalid zero confirmation depth");
+            <ConfirmationDepth<T>>::insert(chain_id, depth);
+            Ok(())
+        }
+
+        /// Register a new eligible blockchain with the bridge. Newly registered
+        /// chains accept deposit and withdraw requests right away.
+        pub fn register_chain(origin, chain_id: ChainId, name: Vec<u8>, format: AddressFormat) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_none(), "Chain already registered");
+
+            <ChainIds<T>>::mutate(|ids| ids.push(chain_id));
+            <Chains<T>>::insert(chain_id, ChainInfo { name, format, enabled: true, evm_chain_id: None });
+            Self::deposit_event(RawEvent::ChainRegistered(chain_id));
+            Ok(())
+        }
+
+        /// Pause or resume the bridge for one direction of a chain. While paused, new
+        /// requests and signatures in that direction are rejected. Callable by root
+        /// or any bridge guardian.
+        pub fn set_paused(origin, chain_id: ChainId, direction: Direction, paused: bool) -> Result {
+            Self::ensure_root_or_guardian(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+            ensure!(Self::paused((chain_id, direction)) != paused, "Pause state unchanged");
+
+            let now = <system::Module<T>>::block_number();
+            <PauseHistory<T>>::mutate((chain_id, direction), |history| {
+                if paused {
+                    history.push((now, None));
+                } else if let Some(period) = history.last_mut() {
+                    period.1 = Some(now);
+                }
+            });
+            <Paused<T>>::insert((chain_id, direction), paused);
+            Self::deposit_event(RawEvent::PauseChanged(chain_id, direction, paused));
+            Ok(())
+        }
+
+        /// Set the rule approving records of a direction of a chain.
+        pub fn set_threshold(origin, chain_id: ChainId, direction: Direction, threshold: ApprovalThreshold) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+
+            match threshold {
+                ApprovalThreshold::StakeFraction(numerator, denominator) => {
+                    ensure!(numerator > 0 && numerator <= denominator, "Invalid stake fraction");
+                },
+                ApprovalThreshold::Count(m) => {
+                    ensure!(m > 0, "Invalid zero signer count");
+                },
+                ApprovalThreshold::Vote(_) | ApprovalThreshold::AuthorityQuorum => {},
+            }
+
+            <Thresholds<T>>::insert((chain_id, direction), threshold);
+            Self::deposit_event(RawEvent::ThresholdChanged(chain_id, direction, threshold));
+            Ok(())
+        }
+
+        /// Set or clear the rolling-window volume limits for a direction of a chain.
+        pub fn set_rate_limit(origin, chain_id: ChainId, direction: Direction, limit: Option<RateLimit<T::Balance, T::BlockNumber>>) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+
+            match limit {
+                Some(limit) => {
+                    ensure!(!limit.window.is_zero(), "Invalid zero rate limit window");
+                    ensure!(limit.max_single <= limit.max_volume, "Invalid single transfer limit exceeding volume limit");
+                    <RateLimits<T>>::insert((chain_id, direction), limit);
+                },
+                None => <RateLimits<T>>::remove((chain_id, direction)),
+            }
+
+            Ok(())
+        }
+
+        /// Set the guardians that can pause and resume the bridge.
+        pub fn set_guardians(origin, guardians: Vec<T::AccountId>) -> Result {
+            ensure_root(origin)?;
+            <Guardians<T>>::put(guardians);
+            Ok(())
+        }
+
+        /// Set the number of blocks after which pending records of a chain can be
+        /// expired. A zero timeout keeps records pending forever.
+        pub fn set_chain_timeout(origin, chain_id: ChainId, timeout: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+
+            <ChainTimeout<T>>::insert(chain_id, timeout);
+            Ok(())
+        }
+
+        /// Set the sources the authority set is drawn from at the next session change.
+        pub fn set_authority_source(origin, source: AuthoritySource) -> Result {
+            ensure_root(origin)?;
+            <AuthorityMode<T>>::put(source);
+            Ok(())
+        }
+
+        /// Approve an account as a governance member of the authority set from the
+        /// next session change on. An approved candidate gets its candidacy bond back.
+        pub fn propose_authority(origin, who: T::AccountId) -> Result {
+            ensure_root(origin)?;
+            ensure!(!Self::governance_authorities().iter().any(|a| a == &who), "Authority already approved");
+
+            if Self::candidates().iter().any(|c| c == &who) {
+                <balances::Module<T>>::unreserve(&who, Self::candidate_deposit(who.clone()));
+                Self::remove_candidate(&who);
+            }
+            <GovernanceAuthorities<T>>::mutate(|members| members.push(who.clone()));
+            Self::deposit_event(RawEvent::AuthorityProposed(who));
+            Ok(())
+        }
+
+        /// Remove a governance member of the authority set from the next session change
+        /// on, or reject a candidate, slashing its candidacy bond.
+        pub fn remove_authority(origin, who: T::AccountId) -> Result {
+            ensure_root(origin)?;
+
+            if Self::candidates().iter().any(|c| c == &who) {
+                let _ = <balances::Module<T>>::slash_reserved(&who, Self::candidate_deposit(who.clone()));
+                Self::remove_candidate(&who);
+            } else {
+                ensure!(Self::governance_authorities().iter().any(|a| a == &who), "Invalid non-member authority");
+                <GovernanceAuthorities<T>>::mutate(|members| members.retain(|a| a != &who));
+            }
+            Self::deposit_event(RawEvent::AuthorityRemoved(who));
+            Ok(())
+        }
+
+        /// Stand as a candidate for governance membership of the authority set. The
+        /// candidacy bond is reserved until governance decides on the candidacy or
+        /// the candidate withdraws.
+        pub fn submit_candidacy(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(!Self::candidates().iter().any(|c| c == &_sender), "Candidacy already submitted");
+            ensure!(!Self::governance_authorities().iter().any(|a| a == &_sender), "Authority already approved");
+
+            let bond = Self::candidacy_bond();
+            <balances::Module<T>>::reserve(&_sender, bond).map_err(|_| "Invalid balance for candidacy bond")?;
+            <CandidateDeposit<T>>::insert(_sender.clone(), bond);
+            <Candidates<T>>::mutate(|candidates| candidates.push(_sender.clone()));
+            Self::deposit_event(RawEvent::CandidacySubmitted(_sender));
+            Ok(())
+        }
+
+        /// Withdraw a pending candidacy and get the candidacy bond back.
+        pub fn withdraw_candidacy(origin) -> Result {
+            let _sender = ensure_signed(origin)?;
+            ensure!(Self::candidates().iter().any(|c| c == &_sender), "Invalid non-candidate sender");
+
+            <balances::Module<T>>::unreserve(&_sender, Self::candidate_deposit(_sender.clone()));
+            Self::remove_candidate(&_sender);
+            Self::deposit_event(RawEvent::CandidacyWithdrawn(_sender));
+            Ok(())
+        }
+
+        /// Set the number of blocks signatures of retired authorities stay valid for
+        /// records opened under their authority set. A zero window drops them as soon
+        /// as a new authority set is installed.
+        pub fn set_handoff_window(origin, chain_id: ChainId, window: T::BlockNumber) -> Result {
+            ensure_root(origin)?;
+            ensure!(<Chains<T>>::get(chain_id).is_some(), "Chain is not registered");
+
+            <HandoffWindow<T>>::insert(chain_id, window);
+            Ok(())
+        }
+
+        /// Enable or disable new deposit and withdraw requests for a registered chain.
+        /// Pending records of a disabled chain can still be signed.
+        pub fn set_chain_enabled(origin, chain_id: ChainId, enabled: bool) -> Result {
+            ensure_root(origin)?;
+
+            match <Chains<T>>::get(chain_id) {
+                Some(mut info) => {
+                    info.enabled = enabled;
+                    <Chains<T>>::insert(chain_id, info);
+                    Self::deposit_event(RawEvent::ChainStatusChanged(chain_id, enabled));
+                },
+                None => { return Err("Chain is not registered") },
+            }
+
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    /// Hash of the withdraw record with an index, if there is one
+    pub fn withdraw_record_hash(index: WithdrawIndex) -> Option<T::Hash> {
+        Self::withdraw_by_index(index)
+    }
+
+    /// Proof of a leaf of the transfer accumulator against its current root, built
+    /// from the stored leaves for relayers to submit to foreign chains
+    #[cfg(feature = "std")]
+    pub fn transfer_proof(leaf_index: u64) -> Option<mmr::Proof> {
+        let leaves = (0..Self::mmr_leaf_count())
+            .map(|index| Self::mmr_leaf(index))
+            .collect::<Option<Vec<_>>>()?;
+        mmr::generate_proof(&leaves, leaf_index)
+    }
+
+    /// Hash of the message authorities sign to approve a withdraw record
+    pub fn withdraw_message(record_hash: T::Hash) -> Option<H256> {
+        Self::withdraw_of(record_hash).map(|record| record.message)
+    }
+
+    /// Encode and hash the message authorities sign under a signature scheme to
+    /// approve a withdraw of `amount` released on the foreign chain, in the layout
+    /// the foreign chain verifies it in. EVM chains get the ABI-packed message or
+    /// EIP-712 typed data hash for their current bridge contract. ECDSA signatures
+    /// over a message hash cannot unlock Bitcoin outputs, so withdraws to Bitcoin
+    /// chains are refused.
+    pub fn encode_withdraw_message(
+        chain_id: ChainId,
+        scheme: SignatureScheme,
+        recipient: &ForeignAddress,
+        amount: T::Balance,
+        index: WithdrawIndex,
+        set_id: AuthoritySetId
+    ) -> rstd::result::Result<H256, &'static str> {
+        match *recipient {
+            ForeignAddress::Evm(ref address) => {
+                let contract = Self::bridge_contract(chain_id).ok_or("Bridge contract not configured")?;
+                let evm_chain_id = Self::chains(chain_id).and_then(|info| info.evm_chain_id).ok_or("EVM chain id not configured")?;
+                let amount = Self::balance_to_u256(amount)?;
+                Ok(Self::evm_withdraw_digest(evm_chain_id, &contract, scheme, address, amount, index, set_id))
+            },
+            ForeignAddress::Bitcoin(_) => Err("Withdraws to Bitcoin chains are not supported"),
+        }
+    }
+
+    /// Digest authorities sign under a signature scheme to approve the release of
+    /// `amount` to a recipient by a bridge contract on an EVM chain
+    fn evm_withdraw_digest(
+        evm_chain_id: u64,
+        contract: &H160,
+        scheme: SignatureScheme,
+        recipient: &H160,
+        amount: U256,
+        nonce: WithdrawIndex,
+        set_id: AuthoritySetId
+    ) -> H256 {
+        match scheme {
+            SignatureScheme::Raw => ethereum::withdraw_message(evm_chain_id, contract, recipient, amount, nonce, set_id),
+            SignatureScheme::Eip712 => ethereum::typed_data_hash(
+                &ethereum::domain_separator(evm_chain_id, contract),
+                &ethereum::withdraw_struct_hash(recipient, amount, nonce, set_id)
+            ),
+        }
+    }
+
+    /// Remember the EIP-155 chain id and bridge contract a chain is configured with,
+    /// so approvals signed for them can still be reported after they change
+    fn record_bridge_domain(chain_id: ChainId) {
+        let evm_chain_id = Self::chains(chain_id).and_then(|info| info.evm_chain_id);
+        if let (Some(evm_chain_id), Some(contract)) = (evm_chain_id, Self::bridge_contract(chain_id)) {
+            <BridgeDomains<T>>::insert((chain_id, evm_chain_id, contract), true);
+        }
+    }
+
+    /// EIP-712 domain separator of the bridge contract of a chain
+    pub fn domain_separator(chain_id: ChainId) -> Option<H256> {
+        let evm_chain_id = Self::chains(chain_id)?.evm_chain_id?;
+        Self::bridge_contract(chain_id).map(|contract| ethereum::domain_separator(evm_chain_id, &contract))
+    }
+
+    /// EIP-712 struct hash of a withdraw record to an EVM chain, approving the
+    /// release of its quantity less its fee
+    pub fn withdraw_struct_hash(record_hash: T::Hash) -> Option<H256> {
+        let record = Self::withdraw_of(record_hash)?;
+        let amount = Self::balance_to_u256(record.quantity - record.fee).ok()?;
+        match record.recipient {
+            ForeignAddress::Evm(address) => Some(ethereum::withdraw_struct_hash(&address, amount, record.index, record.authority_set_id)),
+            _ => None,
+        }
+    }
+
+    /// EIP-712 typed data hash authorities sign to approve a withdraw record with
+    /// the EIP-712 scheme
+    pub fn withdraw_typed_data_hash(record_hash: T::Hash) -> Option<H256> {
+        Self::withdraw_of(record_hash).map(|record| record.typed_data_hash)
+    }
+
+    /// Hash of the message a signing key signs to prove it is controlled by an account
+    pub fn signing_key_proof_message(who: &T::AccountId) -> H256 {
+        let mut message = SIGNING_KEY_PROOF_PREFIX.to_vec();
+        who.encode_to(&mut message);
+        ethereum::keccak256(&message)
+    }
+
+    /// Ensure a signature over a withdraw message was made by the registered signing key of an account
+    fn verify_withdraw_signature(who: &T::AccountId, message: &H256, signature: &[u8]) -> Result {
+        let key = Self::signing_key(who.clone()).ok_or("No signing key registered")?;
+        ensure!(ethereum::recover_address(message, signature)? == key, "Invalid withdraw signature");
+        Ok(())
+    }
+
+    /// The next authority set drawn from the configured sources, along with the
+    /// source of each member
+    fn next_authorities() -> Vec<(T::AccountId, MemberSource)> {
+        let validators = <session::Module<T>>::validators();
+        let governance = Self::governance_authorities();
+
+        match Self::authority_mode() {
+            AuthoritySource::Validators => validators.into_iter()
+                .map(|v| (v, MemberSource::Validator))
+                .collect(),
+            AuthoritySource::Governance => governance.into_iter()
+                .map(|g| (g, MemberSource::Governance))
+                .collect(),
+            AuthoritySource::Union => {
+                let mut members = validators.into_iter()
+                    .map(|v| {
+                        let source = if governance.iter().any(|g| g == &v) {
+                            MemberSource::ValidatorAndGovernance
+                        } else {
+                            MemberSource::Validator
+                        };
+                        (v, source)
+                    })
+                    .collect::<Vec<_>>();
+                for g in governance.into_iter() {
+                    if !members.iter().any(|m| m.0 == g) {
+                        members.push((g, MemberSource::Governance));
+                    }
+                }
+                members
+            },
+        }
+    }
+
+    /// Remove a candidate and its candidacy bond record
+    fn remove_candidate(who: &T::AccountId) {
+        <Candidates<T>>::mutate(|candidates| candidates.retain(|c| c != who));
+        <CandidateDeposit<T>>::remove(who.clone());
+    }
+
+    /// Bonded stake of an account if it is a member of the current authority set
+    pub fn authority_stake(who: &T::AccountId) -> T::Balance {
+        if Self::authorities().iter().any(|a| a == who) {
+            Self::bonded(who.clone())
+        } else {
+            Zero::zero()
+        }
+    }
+
+    /// Total bonded stake of the current authority set
+    pub fn total_authority_stake() -> T::Balance {
+        Self::total_stake(&Self::authorities())
+    }
+
+    /// Total bonded stake of a set of authorities
+    fn total_stake(electorate: &[T::AccountId]) -> T::Balance {
+        electorate.iter()
+            .map(|a| Self::bonded(a.clone()))
+            .fold(Zero::zero(), |a, b| a + b)
+    }
+
+    /// Split the fee of an approved record among its signers. The earliest signers
+    /// additionally share the configured bonus part of the fee. Indivisible
+    /// remainders are not paid out.
+    fn reward_signers(signers: &[T::AccountId], fee: T::Balance) {
+        if signers.is_empty() || fee.is_zero() {
+            return;
+        }
+
+        let bonus_count = cmp::min(Self::bonus_signers() as usize, signers.len());
+        let bonus_pool = if bonus_count > 0 { Self::signer_bonus() * fee } else { Zero::zero() };
+        let base_share = (fee - bonus_pool) / <T::Balance as As<u64>>::sa(signers.len() as u64);
+        let bonus_share = if bonus_count > 0 {
+            bonus_pool / <T::Balance as As<u64>>::sa(bonus_count as u64)
+        } else {
+            Zero::zero()
+        };
+
+        for (i, signer) in signers.iter().enumerate() {
+            let reward = if i < bonus_count { base_share + bonus_share } else { base_share };
+            if !reward.is_zero() {
+                <Rewards<T>>::mutate(signer.clone(), |r| *r = *r + reward);
+            }
+        }
+    }
+
+    /// Slash the bonded and unbonding stake of an authority and remove it from the
+    /// authority set until the next session change.
+    fn slash_authority(who: &T::AccountId) {
+        let unbonding = Self::unbonding(who.clone()).iter()
+            .fold(Zero::zero(), |a: T::Balance, c| a + c.0);
+        let value = Self::bonded(who.clone()) + unbonding;
+
+        // Offenders without stake left still lose their seat
+        let _ = <balances::Module<T>>::slash_reserved(who, value);
+        <Bonded<T>>::remove(who.clone());
+        <Unbonding<T>>::remove(who.clone());
+        <Authorities<T>>::mutate(|authorities| authorities.retain(|a| a != who));
+        <AuthorityQuorum<T>>::put(Self::default_quorum(Self::authorities().len()));
+        // Signatures of the offender don't remain valid through a handoff either
+        for set_id in Self::oldest_authority_set()..Self::authority_set_id() {
+            if let Some(mut set) = Self::authority_set(set_id) {
+                if set.0.iter().any(|a| a == who) {
+                    set.0.retain(|a| a != who);
+                    <AuthoritySets<T>>::insert(set_id, set);
+                }
+            }
+        }
+        Self::deposit_event(RawEvent::Slashed(who.clone(), value));
+    }
+
+    /// Check if a set of signers approves a request of a direction of a chain opened
+    /// under the given authority set. Records opened under a retired set are also
+    /// approved by that set while its signatures are valid in the handoff window.
+    fn is_approved(chain_id: ChainId, direction: Direction, set_id: AuthoritySetId, signers: &[T::AccountId]) -> bool {
+        if Self::is_approved_by(chain_id, direction, &Self::authorities(), Self::quorum(), signers) {
+            return true;
+        }
+
+        match Self::handoff_set(chain_id, set_id) {
+            Some(retired) => {
+                let quorum = Self::default_quorum(retired.len());
+                Self::is_approved_by(chain_id, direction, &retired, quorum, signers)
+            },
+            None => false,
+        }
+    }
+
+    /// Check if a set of signers approves a request of a direction of a chain within
+    /// an authority set
+    fn is_approved_by(chain_id: ChainId, direction: Direction, electorate: &[T::AccountId], quorum: u32, signers: &[T::AccountId]) -> bool {
+        let stake_sum = Self::signed_stake(electorate, signers);
+        let total_stake = Self::total_stake(electorate);
+
+        match Self::threshold((chain_id, direction)) {
+            ApprovalThreshold::StakeFraction(numerator, denominator) => {
+                !total_stake.is_zero() &&
+                    stake_sum * <T::Balance as As<u64>>::sa(denominator as u64) >= total_stake * <T::Balance as As<u64>>::sa(numerator as u64)
+            },
+            ApprovalThreshold::Vote(threshold) => {
+                threshold.approved(stake_sum, total_stake - stake_sum, total_stake, total_stake)
+            },
+            ApprovalThreshold::Count(m) => Self::signer_count(electorate, signers) >= m as usize,
+            ApprovalThreshold::AuthorityQuorum => Self::signer_count(electorate, signers) >= quorum as usize,
+        }
+    }
+
+    /// Members of the retired authority set a record was opened under, if their
+    /// signatures are still valid in the handoff window of the chain
+    fn handoff_set(chain_id: ChainId, set_id: AuthoritySetId) -> Option<Vec<T::AccountId>> {
+        let window = Self::handoff_window(chain_id);
+        if set_id == Self::authority_set_id() || window.is_zero() {
+            return None;
+        }
+
+        let now = <system::Module<T>>::block_number();
+        Self::authority_set(set_id).and_then(|(members, retired_at)| {
+            if now < retired_at + window { Some(members) } else { None }
+        })
+    }
+
+    /// Check if an account can sign a record opened under the given authority set
+    fn can_sign(chain_id: ChainId, set_id: AuthoritySetId, who: &T::AccountId) -> bool {
+        Self::authorities().iter().any(|a| a == who) ||
+            Self::handoff_set(chain_id, set_id).map_or(false, |members| members.iter().any(|a| a == who))
+    }
+
+    /// Count a non-final record opened under an authority set
+    fn reserve_authority_set(set_id: AuthoritySetId) {
+        <OpenRecords<T>>::mutate(set_id, |count| *count += 1);
+    }
+
+    /// Stop counting a record opened under an authority set once it is final
+    fn release_authority_set(set_id: AuthoritySetId) {
+        <OpenRecords<T>>::mutate(set_id, |count| *count = count.saturating_sub(1));
+    }
+
+    /// Remove the snapshots of retired authority sets, oldest first, once they are
+    /// past the handoff window of every chain and every record opened under them
+    /// is final
+    fn prune_authority_sets(now: T::BlockNumber) {
+        let window = Self::chain_ids().into_iter()
+            .map(|chain_id| Self::handoff_window(chain_id))
+            .fold(Zero::zero(), |a: T::BlockNumber, w| if w > a { w } else { a });
+
+        let oldest = Self::oldest_authority_set();
+        let mut set_id = oldest;
+        while set_id < Self::authority_set_id() {
+            if let Some((_, retired_at)) = Self::authority_set(set_id) {
+                if now < retired_at + window || Self::open_records(set_id) > 0 {
+                    break;
+                }
+                <AuthoritySets<T>>::remove(set_id);
+                Self::deposit_event(RawEvent::AuthoritySetPruned(set_id));
+            }
+            <OpenRecords<T>>::remove(set_id);
+            set_id += 1;
+        }
+        if set_id != oldest {
+            <OldestAuthoritySet<T>>::put(set_id);
+        }
+    }
+
+    /// Check if the signers of a record opened under the given authority set are still
+    /// below a fraction of its approval threshold, in every authority set that could
+    /// approve it as in `is_approved`
+    fn is_below_threshold_fraction(chain_id: ChainId, direction: Direction, set_id: AuthoritySetId, signers: &[T::AccountId], fraction: Permill) -> bool {
+        if !Self::is_below_threshold_fraction_of(chain_id, direction, &Self::authorities(), Self::quorum(), signers, fraction) {
+            return false;
+        }
+
+        match Self::handoff_set(chain_id, set_id) {
+            Some(retired) => {
+                let quorum = Self::default_quorum(retired.len());
+                Self::is_below_threshold_fraction_of(chain_id, direction, &retired, quorum, signers, fraction)
+            },
+            None => true,
+        }
+    }
+
+    /// Check if a set of signers is below a fraction of the approval threshold of a
+    /// direction of a chain within an authority set
+    fn is_below_threshold_fraction_of(
+        chain_id: ChainId,
+        direction: Direction,
+        electorate: &[T::AccountId],
+        quorum: u32,
+        signers: &[T::AccountId],
+        fraction: Permill
+    ) -> bool {
+        let threshold = Self::threshold((chain_id, direction));
+        match threshold {
+            ApprovalThreshold::Count(m) => (Self::signer_count(electorate, signers) as u64) < fraction * (m as u64),
+            ApprovalThreshold::AuthorityQuorum => (Self::signer_count(electorate, signers) as u64) < fraction * (quorum as u64),
+            _ => Self::signed_stake(electorate, signers) < fraction * Self::threshold_stake(threshold, Self::total_stake(electorate)),
+        }
+    }
+
+    /// Number of distinct authorities needed to approve by authority quorum. Until the
+    /// first session change the quorum is derived from the genesis authority set.
+    pub fn quorum() -> u32 {
+        match Self::authority_quorum() {
+            0 => Self::default_quorum(Self::authorities().len()),
+            quorum => quorum,
+        }
+    }
+
+    /// Default authority quorum of ceil(2n/3) for a set of `n` authorities
+    fn default_quorum(n: usize) -> u32 {
+        ((2 * n + 2) / 3) as u32
+    }
+
+    /// Number of members of an authority set among a set of signers
+    fn signer_count(electorate: &[T::AccountId], signers: &[T::AccountId]) -> usize {
+        signers.iter().filter(|s| electorate.iter().any(|a| a == *s)).count()
+    }
+
+    /// Bonded stake held by the members of an authority set among a set of signers
+    fn signed_stake(electorate: &[T::AccountId], signers: &[T::AccountId]) -> T::Balance {
+        signers.iter()
+            .filter(|s| electorate.iter().any(|a| a == *s))
+            .map(|s| Self::bonded(s.clone()))
+            .fold(Zero::zero(), |a, b| a + b)
+    }
+
+    /// Stake the signers of a request need for approval by a stake-weighted threshold
+    /// out of the total stake of an authority set. For democracy thresholds this is
+    /// the smallest stake the vote threshold approves with full turnout.
+    fn threshold_stake(threshold: ApprovalThreshold, total_stake: T::Balance) -> T::Balance {
+        match threshold {
+            ApprovalThreshold::StakeFraction(numerator, denominator) => {
+                total_stake * <T::Balance as As<u64>>::sa(numerator as u64) / <T::Balance as As<u64>>::sa(denominator as u64)
+            },
+            ApprovalThreshold::Vote(vote) => {
+                let (mut low, mut high): (T::Balance, T::Balance) = (Zero::zero(), total_stake);
+                while low < high {
+                    let mid = low + (high - low) / <T::Balance as As<u64>>::sa(2);
+                    if vote.approved(mid, total_stake - mid, total_stake, total_stake) {
+                        high = mid;
+                    } else {
+                        low = mid + <T::Balance as As<u64>>::sa(1);
+                    }
+                }
+                low
+            },
+            _ => total_stake,
+        }
+    }
+
+    /// Check if a record of a chain created at the given block has timed out
+    fn is_timed_out(chain_id: ChainId, created_at: T::BlockNumber) -> bool {
+        let timeout = Self::chain_timeout(chain_id);
+        !timeout.is_zero() && <system::Module<T>>::block_number() >= created_at + timeout
+    }
+
+    /// Ensure a record can still be signed
+    fn ensure_pending(status: Status, chain_id: ChainId, created_at: T::BlockNumber) -> Result {
+        match status {
+            Status::Pending => {
+                ensure!(!Self::is_timed_out(chain_id, created_at), "Transaction expired");
+                Ok(())
+            },
+            Status::Approved => Err("Transaction already approved"),
+            Status::Executed | Status::Failed => Err("Transaction already completed"),
+            Status::Expired => Err("Transaction expired"),
+            Status::Cancelled => Err("Transaction cancelled"),
+            Status::Rejected => Err("Transaction rejected"),
+        }
+    }
+
+    /// Volume that can still be executed in the current rolling window of a direction
+    /// of a chain, or `None` if the direction is not rate limited
+    pub fn remaining_capacity(chain_id: ChainId, direction: Direction) -> Option<T::Balance> {
+        Self::rate_limit((chain_id, direction)).map(|limit| {
+            let used = Self::window_volume(chain_id, direction, limit.window);
+            if used >= limit.max_volume { Zero::zero() } else { limit.max_volume - used }
+        })
+    }
+
+    /// Volume executed within the rolling window ending at the current block
+    fn window_volume(chain_id: ChainId, direction: Direction, window: T::BlockNumber) -> T::Balance {
+        let now = <system::Module<T>>::block_number();
+        Self::executed_volume((chain_id, direction)).iter()
+            .filter(|&&(at, _)| at + window > now)
+            .fold(Zero::zero(), |a, v| a + v.1)
+    }
+
+    /// Check if a quantity fits in the remaining capacity of a direction of a chain
+    fn has_capacity(chain_id: ChainId, direction: Direction, quantity: T::Balance) -> bool {
+        match Self::remaining_capacity(chain_id, direction) {
+            Some(remaining) => quantity <= remaining,
+            None => true,
+        }
+    }
+
+    /// Ensure a quantity does not exceed the maximum single transfer of a direction of a chain
+    fn ensure_within_single_limit(chain_id: ChainId, direction: Direction, quantity: T::Balance) -> Result {
+        if let Some(limit) = Self::rate_limit((chain_id, direction)) {
+            ensure!(quantity <= limit.max_single, "Quantity exceeds maximum single transfer");
+        }
+        Ok(())
+    }
+
+    /// Record an executed quantity in the rolling window of a direction of a chain
+    fn record_volume(chain_id: ChainId, direction: Direction, quantity: T::Balance) {
+        if let Some(limit) = Self::rate_limit((chain_id, direction)) {
+            let now = <system::Module<T>>::block_number();
+            <ExecutedVolume<T>>::mutate((chain_id, direction), |volume| {
+                volume.retain(|&(at, _)| at + limit.window > now);
+                volume.push((now, quantity));
+            });
+        }
+    }
+
+    /// Queue an approved record until the rate limits allow its execution
+    fn enqueue(chain_id: ChainId, direction: Direction, hash: T::Hash) {
+        <Queued<T>>::mutate((chain_id, direction), |queue| queue.push(hash));
+        Self::deposit_event(RawEvent::Queued(chain_id, direction, hash));
+    }
+
+    /// Execute queued records of a direction of a chain in order, as long as they
+    /// fit in the remaining capacity
+    fn process_queue(chain_id: ChainId, direction: Direction) {
+        let mut queue = Self::queued((chain_id, direction));
+        if queue.is_empty() || Self::paused((chain_id, direction)) {
+            return;
+        }
+
+        let mut processed = 0;
+        for hash in queue.iter() {
+            let quantity = match direction {
+                Direction::Deposit => Self::deposit_of((chain_id, *hash)).map(|r| r.quantity),
+                Direction::Withdraw => Self::withdraw_of(*hash).map(|r| r.quantity),
+            };
+            if let Some(quantity) = quantity {
+                if !Self::has_capacity(chain_id, direction, quantity) {
+                    break;
+                }
+                let res = match direction {
+                    Direction::Deposit => Self::execute_deposit(chain_id, *hash),
+                    Direction::Withdraw => Self::execute_withdraw(*hash),
+                };
+                if res.is_err() {
+                    Self::fail_record(chain_id, direction, *hash);
+                }
+            }
+            processed += 1;
+        }
+
+        <Queued<T>>::insert((chain_id, direction), queue.split_off(processed));
+    }
+
+    /// Mint an approved deposit less its fee, which is paid out to the signers. Deposits
+    /// without signers are minted in full.
+    fn execute_deposit(chain_id: ChainId, transaction_hash: T::Hash) -> Result {
+        let mut record = Self::deposit_of((chain_id, transaction_hash)).ok_or("Invalid transaction hash")?;
+        ensure!(record.status == Status::Approved, "Transaction not approved");
+        record.status = record.status.transition(Status::Executed)?;
+
+        let qty = record.quantity;
+        // Deposits backed by a proof alone have no signers to pay a fee to
+        let fee = if record.signers.is_empty() { Zero::zero() } else { Self::deposit_fee() * qty };
+        <balances::Module<T>>::increase_free_balance_creating(&record.target, qty - fee);
+        Self::reward_signers(&record.signers, fee);
+        Self::record_volume(record.chain_id, Direction::Deposit, qty);
+        Self::accumulate_transfer(Direction::Deposit, chain_id, transaction_hash, &record);
+        Self::release_authority_set(record.authority_set_id);
+        <DepositOf<T>>::insert((chain_id, transaction_hash), record.clone());
+        Self::schedule_pruning(Direction::Deposit, chain_id, transaction_hash);
+        Self::deposit_event(RawEvent::Approved(record.chain_id, record.index, record.target, qty, record.signers));
+        Ok(())
+    }
+
+    /// Layout version of the bridge storage. Genesis always stores one, so a missing
+    /// version means the chain was upgraded from the baseline layout, which is version 0.
+    pub fn layout_version() -> StorageVersionId {
+        if <StorageVersion<T>>::exists() {
+            Self::storage_version()
+        } else {
+            0
+        }
+    }
+
+    /// Run the next chunk of the migration from the stored storage version, moving
+    /// on to the next version once every item of the current step is migrated
+    fn migrate_storage() {
+        let version = Self::layout_version();
+        let cursor = Self::migration_cursor();
+        let batch = cmp::max(Self::migration_batch_size(), 1);
+
+        let done = match version {
+            0 => Self::migrate_legacy_lists(cursor, batch, Self::migrate_deposit, Self::migrate_withdraw),
+            1 => {
+                let done = Self::migrate_legacy_lists(cursor, batch, Self::index_deposit, Self::index_withdraw);
+                if done {
+                    runtime_support::storage::kill(LEGACY_DEPOSITS_KEY);
+                    runtime_support::storage::kill(LEGACY_WITHDRAWS_KEY);
+                }
+                done
+            },
+            2 => Self::schedule_final_records(cursor, batch),
+            _ => true,
+        };
+
+        if done {
+            <StorageVersion<T>>::put(version + 1);
+            <MigrationCursor<T>>::kill();
+            Self::deposit_event(RawEvent::MigrationCompleted(version + 1));
+        } else {
+            <MigrationCursor<T>>::put(cursor + batch);
+        }
+    }
+
+    /// Migrate up to `count` records listed in the legacy deposit and withdraw lists,
+    /// starting at position `start` of the deposits followed by the withdraws.
+    /// Returns whether every listed record has been migrated.
+    fn migrate_legacy_lists(start: u32, count: u32, deposit: fn(T::Hash), withdraw: fn(T::Hash)) -> bool {
+        let deposits = Self::legacy_list_len(LEGACY_DEPOSITS_KEY);
+        let withdraws = Self::legacy_list_len(LEGACY_WITHDRAWS_KEY);
+        let total = deposits.saturating_add(withdraws);
+        let end = cmp::min(start.saturating_add(count), total);
+
+        for position in start..end {
+            if position < deposits {
+                if let Some(hash) = Self::legacy_list_item(LEGACY_DEPOSITS_KEY, deposits, position) {
+                    deposit(hash);
+                }
+            } else if let Some(hash) = Self::legacy_list_item(LEGACY_WITHDRAWS_KEY, withdraws, position - deposits) {
+                withdraw(hash);
+            }
+        }
+
+        end == total
+    }
+
+    /// Number of hashes in a legacy list, read from its length prefix alone
+    fn legacy_list_len(key: &[u8]) -> u32 {
+        let mut prefix = [0u8; 5];
+        match runtime_io::read_storage(&runtime_io::twox_128(key)[..], &mut prefix, 0) {
+            Some(_) => <Compact<u32>>::decode(&mut &prefix[..]).map_or(0, |len| len.0),
+            None => 0,
+        }
+    }
+
+    /// Hash at a position of a legacy list of `len` hashes, read without reading the
+    /// rest of the list. Hashes follow the length prefix at a fixed size.
+    fn legacy_list_item(key: &[u8], len: u32, position: u32) -> Option<T::Hash> {
+        let item_len = T::Hash::default().encode().len();
+        let offset = Compact(len).encode().len() + position as usize * item_len;
+        let mut item = vec![0u8; item_len];
+        runtime_io::read_storage(&runtime_io::twox_128(key)[..], &mut item, offset)?;
+        Decode::decode(&mut &item[..])
+    }
+
+    /// Index a deposit record by the index it was created with. Records can have
+    /// been removed from the legacy lists, so their positions are not used.
+    fn index_deposit(hash: T::Hash) {
+        let key = (Self::legacy_chain_id(), hash);
+        if let Some(record) = Self::deposit_of(key) {
+            <DepositByIndex<T>>::insert(record.index, key);
+        }
+    }
+
+    /// Index a withdraw record by the index it was created with
+    fn index_withdraw(key: T::Hash) {
+        if let Some(record) = Self::withdraw_of(key) {
+            <WithdrawByIndex<T>>::insert(record.index, key);
+        }
+    }
+
+    /// Move a deposit record keyed by its transaction hash alone under the legacy
+    /// chain, so completed deposits cannot be submitted again on that chain
+    fn migrate_deposit(hash: T::Hash) {
+        let mut raw_key = LEGACY_DEPOSIT_OF_PREFIX.to_vec();
+        hash.encode_to(&mut raw_key);
+        let old = runtime_support::storage::get::<LegacyDepositRecord<T::AccountId, T::Balance>>(&raw_key[..]);
+        if let Some((index, target, quantity, signers, completed)) = old {
+            let chain_id = Self::legacy_chain_id();
+            runtime_support::storage::kill(&raw_key[..]);
+            if !completed {
+                Self::reserve_authority_set(Self::authority_set_id());
+            }
+            <DepositOf<T>>::insert((chain_id, hash), DepositRecord {
+                index,
+                chain_id,
+                target,
+                quantity,
+                signers,
+                status: if completed { Status::Executed } else { Status::Pending },
+                created_at: <system::Module<T>>::block_number(),
+                authority_set_id: Self::authority_set_id(),
+            });
+        }
+    }
+
+    /// Convert a withdraw record stored as a tuple. Such withdraws recorded neither a
+    /// recipient nor a signed message, and reserved nothing, so completed ones are
+    /// kept as executed and pending ones are cancelled.
+    fn migrate_withdraw(key: T::Hash) {
+        let raw_key = <WithdrawOf<T>>::key_for(&key);
+        let old = runtime_support::storage::get::<LegacyWithdrawRecord<T::AccountId, T::Balance>>(&raw_key[..]);
+        if let Some((index, sender, quantity, signatures, completed)) = old {
+            <WithdrawOf<T>>::insert(key, WithdrawRecord {
+                index,
+                chain_id: Self::legacy_chain_id(),
+                sender,
+                quantity,
+                fee: Zero::zero(),
+                recipient: ForeignAddress::Evm(H160::zero()),
+                signatures: signatures.into_iter().map(|(who, signature)| (who, SignatureScheme::Raw, signature)).collect(),
+                status: if completed { Status::Executed } else { Status::Cancelled },
+                created_at: <system::Module<T>>::block_number(),
+                authority_set_id: Self::authority_set_id(),
+                message: H256::zero(),
+                typed_data_hash: H256::zero(),
+            });
+        }
+    }
+
+    /// Schedule pruning of the final records created before they were pruned, taking
+    /// up to `count` records starting at position `start` of the deposits followed by
+    /// the withdraws. Returns whether every record has been visited.
+    fn schedule_final_records(start: u32, count: u32) -> bool {
+        let deposits = Self::deposit_count();
+        let total = deposits + Self::withdraw_count();
+        let end = cmp::min(start.saturating_add(count), total);
+
+        for position in start..end {
+            if position < deposits {
+                if let Some((chain_id, hash)) = Self::deposit_by_index(position) {
+                    if Self::deposit_of((chain_id, hash)).map_or(false, |r| r.status.is_final()) {
+                        Self::schedule_pruning(Direction::Deposit, chain_id, hash);
+                    }
+                }
+            } else if let Some(hash) = Self::withdraw_by_index(position - deposits) {
+                if let Some(record) = Self::withdraw_of(hash) {
+                    if record.status.is_final() {
+                        Self::schedule_pruning(Direction::Withdraw, record.chain_id, hash);
+                    }
+                }
+            }
+        }
+
+        end == total
+    }
+
+    /// Schedule a record that reached a final status to be pruned once the record
+    /// retention period has passed, or retain it until a retention period is set
+    fn schedule_pruning(direction: Direction, chain_id: ChainId, hash: T::Hash) {
+        let retention = Self::record_retention();
+        if retention.is_zero() {
+            let index = Self::retained_count();
+            <RetainedRecord<T>>::insert(index, (direction, chain_id, hash));
+            <RetainedCount<T>>::put(index + 1);
+            return;
+        }
+        let prune_at = <system::Module<T>>::block_number() + retention;
+        <PruneQueue<T>>::mutate(prune_at, |records| records.push((direction, chain_id, hash)));
+    }
+
+    /// Schedule a batch of the records retained while the record retention was zero
+    /// to be pruned once the retention period set since has passed
+    fn schedule_retained_records(now: T::BlockNumber) {
+        let retention = Self::record_retention();
+        let cursor = Self::retained_cursor();
+        let count = Self::retained_count();
+        if retention.is_zero() || cursor >= count {
+            return;
+        }
+
+        let end = cmp::min(cursor + cmp::max(Self::migration_batch_size(), 1) as u64, count);
+        let records = (cursor..end)
+            .filter_map(|index| <RetainedRecord<T>>::take(index))
+            .collect::<Vec<_>>();
+        <PruneQueue<T>>::mutate(now + retention, |queue| queue.extend(records));
+        <RetainedCursor<T>>::put(end);
+    }
+
+    /// Prune the records scheduled for blocks before `now` that were held back by a
+    /// migration, a batch of blocks at a time
+    fn prune_held_records(now: T::BlockNumber) {
+        let mut block = match Self::pruning_held_from() {
+            Some(block) => block,
+            None => return,
+        };
+        for _ in 0..cmp::max(Self::migration_batch_size(), 1) {
+            if block >= now {
+                break;
+            }
+            Self::prune_records(block);
+            block = block + <T::BlockNumber as As<u64>>::sa(1);
+        }
+
+        if block >= now {
+            <PruningHeldFrom<T>>::kill();
+        } else {
+            <PruningHeldFrom<T>>::put(block);
+        }
+    }
+
+    /// Replace the records scheduled to be pruned at a block with commitments to
+    /// their final state. Deposit signers are kept so fraudulent deposits can still
+    /// be reported.
+    fn prune_records(now: T::BlockNumber) {
+        for (direction, chain_id, hash) in <PruneQueue<T>>::take(now) {
+            match direction {
+                Direction::Deposit => {
+                    if let Some(record) = Self::deposit_of((chain_id, hash)) {
+                        let commitment = T::Hashing::hash_of(&record);
+                        <DepositOf<T>>::remove((chain_id, hash));
+                        <DepositByIndex<T>>::remove(record.index);
+                        <DepositCommitment<T>>::insert((chain_id, hash), (commitment, record.signers));
+                        Self::deposit_event(RawEvent::DepositPruned(chain_id, hash, commitment));
+                    }
+                },
+                Direction::Withdraw => {
+                    if let Some(record) = Self::withdraw_of(hash) {
+                        let commitment = T::Hashing::hash_of(&record);
+                        <WithdrawOf<T>>::remove(hash);
+                        <WithdrawByIndex<T>>::remove(record.index);
+                        <WithdrawCommitment<T>>::insert(hash, commitment);
+                        Self::deposit_event(RawEvent::WithdrawPruned(chain_id, hash, commitment));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Append an executed record to the accumulator of transfers. Approvals that fail
+    /// are never committed to. The leaf commits to the direction, chain and key of
+    /// the record along with its encoding.
+    fn accumulate_transfer<R: Encode>(direction: Direction, chain_id: ChainId, hash: T::Hash, record: &R) {
+        let mut data = (direction, chain_id, hash).encode();
+        data.extend(record.encode());
+        let leaf = ethereum::keccak256(&data);
+        let leaf_index = Self::mmr_leaf_count();
+        let mut peaks = Self::mmr_peaks();
+        mmr::append(&mut peaks, leaf_index, leaf);
+        <MmrPeaks<T>>::put(peaks);
+        <MmrLeaf<T>>::insert(leaf_index, leaf);
+        <MmrLeafCount<T>>::put(leaf_index + 1);
+        Self::deposit_event(RawEvent::TransferAccumulated(direction, chain_id, hash, leaf_index));
+    }
+
+    /// Mark an approved record that could not be executed as failed, returning the
+    /// funds still reserved for a withdraw to its requester
+    fn fail_record(chain_id: ChainId, direction: Direction, hash: T::Hash) {
+        match direction {
+            Direction::Deposit => {
+                if let Some(mut record) = Self::deposit_of((chain_id, hash)) {
+                    if let Ok(status) = record.status.transition(Status::Failed) {
+                        record.status = status;
+                        Self::release_authority_set(record.authority_set_id);
+                        <DepositOf<T>>::insert((chain_id, hash), record);
+                        Self::schedule_pruning(direction, chain_id, hash);
+                        Self::deposit_event(RawEvent::TransferFailed(chain_id, direction, hash));
+                    }
+                }
+            },
+            Direction::Withdraw => {
+                if let Some(mut record) = Self::withdraw_of(hash) {
+                    if let Ok(status) = record.status.transition(Status::Failed) {
+                        record.status = status;
+                        <balances::Module<T>>::unreserve(&record.sender, record.quantity);
+                        Self::release_authority_set(record.authority_set_id);
+                        <WithdrawOf<T>>::insert(hash, record);
+                        Self::schedule_pruning(direction, chain_id, hash);
+                        Self::deposit_event(RawEvent::TransferFailed(chain_id, direction, hash));
+                    }
+                }
+            },
+        }
+    }
+
+    /// Execute an approved deposit right away or queue it until the rate limits allow
+    fn execute_or_enqueue_deposit(chain_id: ChainId, transaction_hash: T::Hash, quantity: T::Balance) -> Result {
+        if Self::queued((chain_id, Direction::Deposit)).is_empty() && Self::has_capacity(chain_id, Direction::Deposit, quantity) {
+            Self::execute_deposit(chain_id, transaction_hash)
+        } else {
+            Self::enqueue(chain_id, Direction::Deposit, transaction_hash);
+            Ok(())
+        }
+    }
+
+    /// Verify that a deposit proof shows the bridge contract of the chain locking
+    /// `quantity` for `target` in the transaction with the given hash.
+    fn verify_deposit_proof(chain_id: ChainId, target: &T::AccountId, transaction_hash: &T::Hash, quantity: T::Balance, proof: &DepositProof) -> Result {
+        match proof {
+            DepositProof::EthereumReceipt(proof) => {
+                let contract = Self::bridge_contract(chain_id).ok_or("Bridge contract not configured")?;
+                let (transactions_root, receipts_root) = Self::block_roots((chain_id, proof.block_hash))
+                    .or_else(|| Self::confirmed_roots(chain_id, proof.block_hash))
+                    .ok_or("Unknown block for deposit proof")?;
+
+                // Transactions and receipts share the same key in their tries
+                let key = rlp::encode_u64(proof.transaction_index);
+                let transaction = ethereum::verify_proof(&transactions_root, &key, &proof.transaction_proof)?;
+                ensure!(ethereum::keccak256(&transaction).as_ref() == transaction_hash.as_ref(), "Transaction hashes do not match");
+                let receipt = ethereum::verify_proof(&receipts_root, &key, &proof.receipt_proof)?;
+                let logs = ethereum::decode_receipt(&receipt)?;
+
+                let encoded_target = target.encode();
+                ensure!(encoded_target.len() <= 32, "Invalid deposit target");
+                let amount = Self::balance_to_u256(quantity)?;
+                let mut recipient = [0u8; 32];
+                recipient[..encoded_target.len()].copy_from_slice(&encoded_target);
+                let recipient = H256::from(recipient);
+                let topic = ethereum::lock_event_topic();
+
+                ensure!(logs.iter().any(|log| {
+                    log.address == contract
+                        && log.topics.len() == 3
+                        && log.topics[0] == topic
+                        && log.topics[2] == recipient
+                        && ethereum::decode_uint(&log.data) == Some(amount)
+                }), "No matching lock event in receipt");
+                Ok(())
+            },
+            DepositProof::BitcoinTransaction(proof) => {
+                let script = Self::federation_script(chain_id);
+                ensure!(!script.is_empty(), "Federation script not configured");
+                let header = Self::confirmed_bitcoin_header(chain_id, proof.block_hash)
+                    .ok_or("Unknown block for deposit proof")?;
+
+                let txid = bitcoin::sha256d(&proof.transaction);
+                ensure!(txid.as_ref() == transaction_hash.as_ref(), "Transaction hashes do not match");
+                ensure!(bitcoin::merkle_root(txid, &proof.merkle_branch, proof.index) == header.merkle_root, "Invalid merkle proof");
+
+                // The federation must be paid exactly the quantity, with the target
+                // committed to in an OP_RETURN output
+                let outputs = bitcoin::decode_outputs(&proof.transaction)?;
+                let paid = outputs.iter()
+                    .filter(|o| o.script == script)
+                    .fold(0u64, |paid, o| paid.saturating_add(o.value));
+                let encoded_target = target.encode();
+                ensure!(outputs.iter().any(|o| bitcoin::op_return_data(&o.script) == Some(&encoded_target[..])), "No deposit target in transaction");
+                let expected: u64 = quantity.as_();
+                ensure!(<T::Balance as As<u64>>::sa(expected) == quantity, "Quantities don't match");
+                ensure!(paid == expected, "Quantities don't match");
+                Ok(())
+            },
+        }
+    }
+
+    /// A balance as a full-width `uint256`, read from its fixed-width little endian encoding
+    fn balance_to_u256(quantity: T::Balance) -> rstd::result::Result<U256, &'static str> {
+        let encoded = quantity.encode();
+        ensure!(encoded.len() <= 32, "Invalid balance width");
+        Ok(U256::from_little_endian(&encoded))
+    }
+
+    /// Store a header along with its total difficulty
+    fn import_header(chain_id: ChainId, hash: H256, header: ethereum::Header, total_difficulty: U256) {
+        let number = header.number;
+        <Headers<T>>::insert((chain_id, hash), (header, total_difficulty));
+        Self::deposit_event(RawEvent::HeaderImported(chain_id, hash, number));
+    }
+
+    /// Make an attested header the head of the canonical chain if it has the most
+    /// total difficulty, updating the canonical hashes back to the fork point.
+    fn select_best_header(chain_id: ChainId, hash: H256) {
+        let (header, total_difficulty) = match Self::header((chain_id, hash)) {
+            Some(header) => header,
+            None => return,
+        };
+        let number = header.number;
+        let best = Self::best_header(chain_id).and_then(|best| Self::header((chain_id, best)));
+        if let Some((_, best_total_difficulty)) = best {
+            if total_difficulty <= best_total_difficulty {
+                return;
+            }
+        }
+
+        Self::set_best_header(chain_id, hash, number, best.map(|b| b.0.number), |h| {
+            let (header, _) = Self::header((chain_id, h))?;
+            Self::header((chain_id, header.parent_hash)).map(|(parent, _)| (header.parent_hash, parent.number))
+        });
+    }
+
+    /// Store a Bitcoin header and make it the head of the canonical chain if it has
+    /// the most chain work
+    fn import_bitcoin_header(chain_id: ChainId, hash: H256, header: bitcoin::Header, height: u32, chain_work: U256, period_start: u32) {
+        <BitcoinHeaders<T>>::insert((chain_id, hash), (header, height, chain_work, period_start));
+        Self::deposit_event(RawEvent::HeaderImported(chain_id, hash, height as u64));
+
+        let best = Self::best_header(chain_id).and_then(|best| Self::bitcoin_header((chain_id, best)));
+        if let Some((_, _, best_chain_work, _)) = best {
+            if chain_work <= best_chain_work {
+                return;
+            }
+        }
+
+        Self::set_best_header(chain_id, hash, height as u64, best.map(|b| b.1 as u64), |h| {
+            let (header, _, _, _) = Self::bitcoin_header((chain_id, h))?;
+            Self::bitcoin_header((chain_id, header.prev_hash)).map(|parent| (header.prev_hash, parent.1 as u64))
+        });
+    }
+
+    /// Make a relayed header the head of the canonical chain of a chain, updating the
+    /// canonical hashes back to the fork point. `parent_of` looks up the hash and
+    /// number of the parent of a relayed header.
+    fn set_best_header<F>(chain_id: ChainId, hash: H256, number: u64, best_number: Option<u64>, parent_of: F)
+        where F: Fn(H256) -> Option<(H256, u64)>
+    {
+        // Drop canonical hashes above the new head if the new chain is shorter
+        if let Some(best_number) = best_number {
+            for n in (number + 1)..(best_number + 1) {
+                <CanonicalHeader<T>>::remove((chain_id, n));
+            }
+        }
+
+        let (mut hash_at, mut number_at) = (hash, number);
+        while Self::canonical_header((chain_id, number_at)) != Some(hash_at) {
+            <CanonicalHeader<T>>::insert((chain_id, number_at), hash_at);
+            match parent_of(hash_at) {
+                Some((parent_hash, parent_number)) => {
+                    hash_at = parent_hash;
+                    number_at = parent_number;
+                },
+                None => break,
+            }
+        }
+        <BestHeader<T>>::insert(chain_id, hash);
+        Self::deposit_event(RawEvent::NewBestHeader(chain_id, hash, number));
+    }
+
+    /// A relayed Bitcoin header of a chain, if it is in the canonical chain and has
+    /// enough confirmations. No header is confirmed until a depth has been set.
+    pub fn confirmed_bitcoin_header(chain_id: ChainId, block_hash: H256) -> Option<bitcoin::Header> {
+        let depth = Self::confirmation_depth(chain_id);
+        let (header, height, _, _) = Self::bitcoin_header((chain_id, block_hash))?;
+        let (_, best_height, _, _) = Self::bitcoin_header((chain_id, Self::best_header(chain_id)?))?;
+        if depth == 0
+            || Self::canonical_header((chain_id, height as u64)) != Some(block_hash)
+            || (best_height as u64) < height as u64 + depth
+        {
+            return None;
+        }
+        Some(header)
+    }
+
+    /// Ensure an account may relay headers
+    fn ensure_relayer(who: &T::AccountId) -> Result {
+        let bonded = Self::bonded(who.clone());
+        ensure!(
+            <Authorities<T>>::get().iter().any(|a| a == who) || (!bonded.is_zero() && bonded >= Self::relayer_bond()),
+            "Invalid unbonded relayer"
+        );
+        Ok(())
+    }
+
+    /// Transactions and receipts roots of a relayed header of a chain, if it is in
+    /// the canonical chain, has enough confirmations and its challenge period has
+    /// passed. No header is confirmed until a depth has been set.
+    pub fn confirmed_roots(chain_id: ChainId, block_hash: H256) -> Option<(H256, H256)> {
+        let depth = Self::confirmation_depth(chain_id);
+        let final_at = Self::header_final_at((chain_id, block_hash))?;
+        let (header, _) = Self::header((chain_id, block_hash))?;
+        let (best, _) = Self::header((chain_id, Self::best_header(chain_id)?))?;
+        if depth == 0
+            || final_at > <system::Module<T>>::block_number()
+            || Self::canonical_header((chain_id, header.number)) != Some(block_hash)
+            || best.number < header.number + depth
+        {
+            return None;
+        }
+        Some((header.transactions_root, header.receipts_root))
+    }
+
+    /// Burn the funds reserved by an approved withdraw and pay its fee out to the signers
+    fn execute_withdraw(record_hash: T::Hash) -> Result {
+        let mut record = Self::withdraw_of(record_hash).ok_or("Invalid record hash")?;
+        ensure!(record.status == Status::Approved, "Transaction not approved");
+        let (chain, tgt, qty) = (record.chain_id, record.sender.clone(), record.quantity);
+        ensure!(<balances::Module<T>>::reserved_balance(&tgt) >= qty, "Insufficient reserved balance for withdraw");
+        record.status = record.status.transition(Status::Executed)?;
+
+        let signer_ids = record.signatures.iter().map(|s| s.0.clone()).collect::<Vec<_>>();
+        let _ = <balances::Module<T>>::slash_reserved(&tgt, qty);
+        Self::reward_signers(&signer_ids, record.fee);
+        Self::record_volume(chain, Direction::Withdraw, qty);
+        Self::accumulate_transfer(Direction::Withdraw, chain, record_hash, &record);
+        Self::release_authority_set(record.authority_set_id);
+        <WithdrawOf<T>>::insert(record_hash, record);
+        Self::schedule_pruning(Direction::Withdraw, chain, record_hash);
+        if let Some(last_signer) = signer_ids.last() {
+            Self::deposit_event(RawEvent::WithdrawSigned(chain, last_signer.clone(), tgt, record_hash, qty));
+        }
+        Ok(())
+    }
+
+    /// Ensure the bridge is not paused for a direction of a chain
+    fn ensure_not_paused(chain_id: ChainId, direction: Direction) -> Result {
+        ensure!(!Self::paused((chain_id, direction)), "Bridge is paused for this chain and direction");
+        Ok(())
+    }
+
+    /// Ensure records are not read or written while they are being migrated
+    fn ensure_migrated() -> Result {
+        ensure!(Self::layout_version() >= STORAGE_VERSION, "Storage migration in progress");
+        Ok(())
+    }
+
+    /// Ensure an origin is root or a signed bridge guardian
+    fn ensure_root_or_guardian(origin: T::Origin) -> Result {
+        match origin.into() {
+            Some(RawOrigin::Root) => Ok(()),
+            Some(RawOrigin::Signed(who)) => {
+                ensure!(Self::guardians().iter().any(|g| g == &who), "Invalid non-guardian sender");
+                Ok(())
+            },
+            _ => Err("Bad origin"),
+        }
+    }
+
+    /// Ensure a chain is registered and currently accepts new requests
+    fn ensure_chain_enabled(chain_id: ChainId) -> Result {
+        match <Chains<T>>::get(chain_id) {
+            Some(info) => {
+                ensure!(info.enabled, "Chain is disabled");
+                Ok(())
+            },
+            None => Err("Chain is not registered"),
+        }
+    }
+
+    /// Ensure a recipient is a well-formed address in the format of the given chain
+    fn ensure_valid_recipient(chain_id: ChainId, recipient: &ForeignAddress) -> Result {
+        let info = <Chains<T>>::get(chain_id).ok_or("Chain is not registered")?;
+        ensure!(recipient.format() == info.format, "Invalid recipient format for chain");
+
+        match *recipient {
+            ForeignAddress::Evm(ref address) => {
+                ensure!(!address.is_zero(), "Invalid zero recipient address");
+            },
+            ForeignAddress::Bitcoin(ref script) => {
+                ensure!(!script.is_empty(), "Invalid empty recipient script");
+                ensure!(bitcoin::op_return_data(script).is_none(), "Invalid unspendable recipient script");
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl<X, T> session::OnSessionChange<X> for Module<T>
+where
+    T: Trait,
+    T: session::Trait,
+{
+    fn on_session_change(_: X, _: bool) {
+        let next_members = Self::next_authorities();
+        let next_authorities = next_members.iter()
+            .map(|m| m.0.clone())
+            .collect::<Vec<T::AccountId>>();
+
+        // instant changes
+        let last_authorities = <Authorities<T>>::get();
+        if next_authorities != last_authorities {
+            // Keep the retiring set around for records opened under it
+            let set_id = Self::authority_set_id();
+            let now = <system::Module<T>>::block_number();
+            <AuthoritySets<T>>::insert(set_id, (last_authorities, now));
+            <CurrentAuthoritySetId<T>>::put(set_id + 1);
+
+            <Authorities<T>>::put(next_authorities.clone());
+            <AuthorityQuorum<T>>::put(Self::default_quorum(next_authorities.len()));
+            Self::deposit_event(RawEvent::NewAuthorities(next_members));
+        }
+    }
+}
+
+
+/// An event in this module.
+decl_event!(
+    pub enum Event<T> where <T as system::Trait>::Hash,
+                            <T as system::Trait>::AccountId,
+                            <T as system::Trait>::BlockNumber,
+                            <T as balances::Trait>::Balance {
+        /// Deposit event for a chain, an account, an eligible blockchain transaction hash, and quantity
+        Deposit(ChainId, AccountId, Hash, Balance),
+        /// Withdraw event for a chain, an account, a foreign recipient, and an amount
+        Withdraw(ChainId, AccountId, ForeignAddress, Balance),
+        /// New authority set has been applied, along with the source of each member.
+        NewAuthorities(Vec<(AccountId, MemberSource)>),
+        /// An account has been approved as a governance member of the authority set
+        AuthorityProposed(AccountId),
+        /// A governance member or candidate has been removed
+        AuthorityRemoved(AccountId),
+        /// An account has submitted its candidacy for the authority set
+        CandidacySubmitted(AccountId),
+        /// A candidate has withdrawn its candidacy
+        CandidacyWithdrawn(AccountId),
+        /// Approved
+        Approved(ChainId, u32, AccountId, Balance, Vec<AccountId>),
+        /// Withdrawl signed
+        WithdrawSigned(ChainId, AccountId, AccountId, Hash, Balance),
+        /// A new eligible blockchain has been registered
+        ChainRegistered(ChainId),
+        /// An eligible blockchain has been enabled or disabled
+        ChainStatusChanged(ChainId, bool),
+        /// Stake has been bonded by an account
+        StakeBonded(AccountId, Balance),
+        /// Stake has been unbonded by an account and unlocks at the given block
+        StakeUnbonded(AccountId, Balance, BlockNumber),
+        /// Unbonded stake has been released to an account
+        UnbondedWithdrawn(AccountId, Balance),
+        /// A pending deposit has timed out
+        DepositExpired(ChainId, Hash),
+        /// A pending withdraw has timed out and its funds have been returned
+        WithdrawExpired(ChainId, Hash),
+        /// A pending withdraw has been cancelled by its requester and its funds have been returned
+        WithdrawCancelled(ChainId, AccountId, Hash),
+        /// The approval threshold of a direction of a chain has changed
+        ThresholdChanged(ChainId, Direction, ApprovalThreshold),
+        /// An approved record has been queued until the rate limits allow its execution
+        Queued(ChainId, Direction, Hash),
+        /// The bridge has been paused or resumed for a direction of a chain
+        PauseChanged(ChainId, Direction, bool),
+        /// Signer rewards have been claimed by an account
+        RewardsClaimed(AccountId, Balance),
+        /// An authority has been slashed for signing conflicting or fraudulent records
+        Slashed(AccountId, Balance),
+        /// An account has registered the address of its withdraw signing key
+        SigningKeyRegistered(AccountId, H160),
+        /// A header of a chain has been imported, with its hash and number
+        HeaderImported(ChainId, H256, u64),
+        /// A header has become the head of the canonical chain of relayed headers
+        NewBestHeader(ChainId, H256, u64),
+        /// A relayed header has been attested by a quorum of authorities
+        HeaderAttested(ChainId, H256),
+        /// An attested header has been challenged and dropped, slashing its attesters
+        HeaderRejected(ChainId, H256),
+        /// The bridge storage has been migrated to a new layout version
+        MigrationCompleted(StorageVersionId),
+        /// A final deposit record has been pruned, leaving a commitment to its final state
+        DepositPruned(ChainId, Hash, Hash),
+        /// A final withdraw record has been pruned, leaving a commitment to its final state
+        WithdrawPruned(ChainId, Hash, Hash),
+        /// The snapshot of a retired authority set has been pruned
+        AuthoritySetPruned(AuthoritySetId),
+        /// An executed record has been appended to the transfer accumulator at a leaf index
+        TransferAccumulated(Direction, ChainId, Hash, u64),
+        /// The root of the transfer accumulator has changed, along with its number of leaves
+        MmrRootUpdated(H256, u64),
+        /// A deposit has been rejected as fraudulent before it was executed
+        DepositRejected(ChainId, Hash),
+        /// An approved record could not be executed
+        TransferFailed(ChainId, Direction, Hash),
+    }
+);
+
+decl_storage! {
+    trait Store for Module<T: Trait> as BridgeStorage {
+        /// Relayed headers of each chain by hash, along with their total difficulty
+        pub Headers get(header): map (ChainId, H256) => Option<(ethereum::Header, U256)>;
+        /// Relayed Bitcoin headers of each chain by hash, along with their height, chain
+        /// work and the time of the first block of their retarget interval
+        pub BitcoinHeaders get(bitcoin_header): map (ChainId, H256) => Option<(bitcoin::Header, u32, U256, u32)>;
+        /// Proof of work limit of each Bitcoin chain, in compact form
+        pub PowLimit get(pow_limit): map ChainId => u32;
+        /// Output script of the federation receiving Bitcoin deposits of each chain
+        pub FederationScript get(federation_script): map ChainId => Vec<u8>;
+        /// Head of the canonical chain of relayed headers of each chain
+        pub BestHeader get(best_header): map ChainId => Option<H256>;
+        /// Canonical relayed header hash of each chain by block number
+        pub CanonicalHeader get(canonical_header): map (ChainId, u64) => Option<H256>;
+        /// Number of relayed headers needed on top of a header before its roots back deposit proofs
+        pub ConfirmationDepth get(confirmation_depth): map ChainId => u64;
+        /// Authorities that attested each relayed header of a chain
+        pub HeaderAttestations get(header_attestations): map (ChainId, H256) => Vec<T::AccountId>;
+        /// Block from which an attested or checkpointed header of a chain can no
+        /// longer be challenged
+        pub HeaderFinalAt get(header_final_at): map (ChainId, H256) => Option<T::BlockNumber>;
+        /// Attested headers of each chain that can still be challenged, in attestation order
+        pub UnfinalHeaders get(unfinal_headers): map ChainId => Vec<H256>;
+        /// Number of blocks attested headers of a chain can be challenged for
+        pub HeaderChallengePeriod get(header_challenge_period): map ChainId => T::BlockNumber;
+        /// Bonded stake a non-authority needs to relay headers
+        pub RelayerBond get(relayer_bond) config(): T::Balance;
+
+        /// The active set of bridge authorities who can sign off on requests
+        pub Authorities get(authorities) config(): Vec<T::AccountId>;
+        /// Sources the authority set is drawn from at each session change
+        pub AuthorityMode get(authority_mode): AuthoritySource;
+        /// Members of the authority set approved by governance
+        pub GovernanceAuthorities get(governance_authorities) config(): Vec<T::AccountId>;
+        /// Candidates waiting for governance approval
+        pub Candidates get(candidates): Vec<T::AccountId>;
+        /// Bond reserved by each candidacy
+        pub CandidacyBond get(candidacy_bond) config(): T::Balance;
+        /// Candidacy bond reserved by each candidate
+        pub CandidateDeposit get(candidate_deposit): map T::AccountId => T::Balance;
+
+        /// Identifier of the current authority set
+        pub CurrentAuthoritySetId get(authority_set_id): AuthoritySetId;
+        /// Members of retired authority sets along with the block they were retired at
+        pub AuthoritySets get(authority_set): map AuthoritySetId => Option<(Vec<T::AccountId>, T::BlockNumber)>;
+        /// Number of non-final records opened under each authority set
+        pub OpenRecords get(open_records): map AuthoritySetId => u32;
+        /// Oldest retired authority set whose snapshot has not been pruned
+        pub OldestAuthoritySet get(oldest_authority_set): AuthoritySetId;
+        /// Number of blocks signatures of retired authorities stay valid for records of a chain
+        pub HandoffWindow get(handoff_window): map ChainId => T::BlockNumber;
+        /// Number of distinct authorities needed to approve by authority quorum,
+        /// recomputed whenever a new authority set is installed
+        pub AuthorityQuorum get(authority_quorum): u32;
+
+        /// List of all registered eligible blockchains
+        pub ChainIds get(chain_ids): Vec<ChainId>;
+        /// Mapping of registered chain identifiers to their registration info
+        pub Chains get(chains): map ChainId => Option<ChainInfo>;
+        /// Bridge contract of each chain whose lock events back deposit proofs
+        pub BridgeContract get(bridge_contract): map ChainId => Option<H160>;
+        /// EIP-155 chain id and bridge contract pairs each chain has been configured with
+        pub BridgeDomains get(is_bridge_domain): map (ChainId, u64, H160) => bool;
+        /// Transactions and receipts roots of known blocks of each chain
+        pub BlockRoots get(block_roots): map (ChainId, H256) => Option<(H256, H256)>;
+        /// Accounts that can pause and resume the bridge alongside root
+        pub Guardians get(guardians) config(): Vec<T::AccountId>;
+        /// Whether the bridge is paused for a direction of a chain
+        pub Paused get(paused): map (ChainId, Direction) => bool;
+        /// Paused periods of a direction of a chain, as the block the bridge was paused
+        /// at and the block it was resumed at, if any
+        pub PauseHistory get(pause_history): map (ChainId, Direction) => Vec<(T::BlockNumber, Option<T::BlockNumber>)>;
+        /// Rule approving records of a direction of a chain
+        pub Thresholds get(threshold): map (ChainId, Direction) => ApprovalThreshold;
+        /// Rolling-window volume limits of a direction of a chain
+        pub RateLimits get(rate_limit): map (ChainId, Direction) => Option<RateLimit<T::Balance, T::BlockNumber>>;
+        /// Quantities executed within the current rolling window of a direction of a chain
+        pub ExecutedVolume get(executed_volume): map (ChainId, Direction) => Vec<(T::BlockNumber, T::Balance)>;
+        /// Approved records of a direction of a chain waiting for capacity, in approval order
+        pub Queued get(queued): map (ChainId, Direction) => Vec<T::Hash>;
+        /// Number of blocks after which pending records of a chain can be expired
+        pub ChainTimeout get(chain_timeout): map ChainId => T::BlockNumber;
+
+        /// Ethereum address of the key each account signs withdraws with
+        pub SigningKeys get(signing_key): map T::AccountId => Option<H160>;
+        /// Account each withdraw signing key is registered to
+        pub SigningKeyOwner get(signing_key_owner): map H160 => Option<T::AccountId>;
+        /// Offences of authorities that have been reported with conflicting withdraw evidence
+        pub ProcessedEvidence get(processed_evidence): map T::Hash => bool;
+        /// Deposits whose signers have been slashed as fraudulent
+        pub ReportedDeposits get(deposit_reported): map (ChainId, T::Hash) => bool;
+        /// Bonded stake of each account, weighing its signatures while it is a bridge authority
+        pub Bonded get(bonded): map T::AccountId => T::Balance;
+        /// Stake being unbonded by each account along with the block it unlocks at
+        pub Unbonding get(unbonding): map T::AccountId => Vec<(T::Balance, T::BlockNumber)>;
+        /// Number of blocks unbonded stake stays reserved before it can be withdrawn
+        pub UnbondingPeriod get(unbonding_period) config(): T::BlockNumber;
+
+        /// Fee taken from the quantity of approved deposits and paid to their signers
+        pub DepositFee get(deposit_fee) config(): Permill;
+        /// Fee taken from the quantity of approved withdraws and paid to their signers.
+        /// The eligible blockchain releases the quantity less this fee.
+        pub WithdrawFee get(withdraw_fee) config(): Permill;
+        /// Number of earliest signers of a record that share the signer bonus
+        pub BonusSigners get(bonus_signers) config(): u32;
+        /// Part of each fee set aside as a bonus for the earliest signers
+        pub SignerBonus get(signer_bonus) config(): Permill;
+        /// Signer rewards accrued by each account and not yet claimed
+        pub Rewards get(rewards): map T::AccountId => T::Balance;
 
         /// Number of deposits
         pub DepositCount get(deposit_count): u32;
-        /// List of all deposit requests on Edgeware taken to be the transaction hash
-        /// from the eligible blockchain
-        pub Deposits get(deposits): Vec<T::Hash>;
-        /// Mapping of deposit transaction hashes from the eligible blockchain to the
+        /// Deposit requests on Edgeware by index, taken to be the chain and the
+        /// transaction hash from the eligible blockchain
+        pub DepositByIndex get(deposit_by_index): map DepositIndex => Option<(ChainId, T::Hash)>;
+        /// Mapping of deposit transaction hashes from an eligible blockchain to the
         /// deposit request record
-        pub DepositOf get(deposit_of): map T::Hash => Option<(DepositIndex, T::AccountId, T::Balance, Vec<T::AccountId>, bool)>;
+        pub DepositOf get(deposit_of): map (ChainId, T::Hash) => Option<DepositRecord<T::AccountId, T::Balance, T::BlockNumber>>;
+        /// Chain the records created before chains were registered are migrated under
+        pub LegacyChainId get(legacy_chain_id) config(): ChainId;
 
         /// Number of withdraws
         pub WithdrawCount get(withdraw_count): u32;
-        /// List of all withdraw requests on Edgeware taken to be the unique hash created
-        /// on Edgeware with the user's account, quantity, and nonce
-        pub Withdraws get(withdraws): Vec<T::Hash>;
+        /// Withdraw requests on Edgeware by index, taken to be the unique hash created
+        /// on Edgeware with the chain, the user's account, quantity, and nonce
+        pub WithdrawByIndex get(withdraw_by_index): map WithdrawIndex => Option<T::Hash>;
         /// Mapping of withdraw record hashes to the record
-        pub WithdrawOf get(withdraw_of): map T::Hash => Option<(WithdrawIndex, T::AccountId, T::Balance, Vec<(T::AccountId, Vec<u8>)>, bool)>;
+        pub WithdrawOf get(withdraw_of): map T::Hash => Option<WithdrawRecord<T::AccountId, T::Balance, T::BlockNumber>>;
+        /// Leaves of the accumulator of executed transfers by index, kept for proof generation
+        pub MmrLeaf get(mmr_leaf): map u64 => Option<H256>;
+        /// Number of leaves of the transfer accumulator
+        pub MmrLeafCount get(mmr_leaf_count): u64;
+        /// Peaks of the mountains of the transfer accumulator, from the highest to the lowest
+        pub MmrPeaks get(mmr_peaks): Vec<H256>;
+        /// Root of the transfer accumulator as of the end of the last block
+        pub MmrRoot get(mmr_root): H256;
+        /// Hashes of the final state of pruned deposit records along with their signers
+        pub DepositCommitment get(deposit_commitment): map (ChainId, T::Hash) => Option<(T::Hash, Vec<T::AccountId>)>;
+        /// Hashes of the final state of pruned withdraw records
+        pub WithdrawCommitment get(withdraw_commitment): map T::Hash => Option<T::Hash>;
+        /// Number of blocks final records are kept for before they are pruned, or zero
+        /// to keep them until it is set
+        pub RecordRetention get(record_retention) config(): T::BlockNumber;
+        /// Final records to prune at each block, by direction, chain and key
+        pub PruneQueue get(prune_queue): map T::BlockNumber => Vec<(Direction, ChainId, T::Hash)>;
+        /// Final records reached while the record retention was zero, by direction, chain
+        /// and key, in the order they were retained
+        pub RetainedRecord get(retained_record): map u64 => Option<(Direction, ChainId, T::Hash)>;
+        /// Number of final records ever retained while the record retention was zero
+        pub RetainedCount get(retained_count): u64;
+        /// Position of the next retained record to schedule for pruning
+        pub RetainedCursor get(retained_cursor): u64;
+        /// First block whose records were not pruned because storage was being migrated
+        pub PruningHeldFrom get(pruning_held_from): Option<T::BlockNumber>;
+        /// Fraction of the approval stake below which a withdraw can still be cancelled
+        pub CancelThreshold get(cancel_threshold) config(): Permill;
+        /// Layout version of the bridge storage, behind `STORAGE_VERSION` while a
+        /// migration is in progress. Chains upgraded from the baseline layout never
+        /// stored it, see `layout_version`.
+        pub StorageVersion get(storage_version) config(): StorageVersionId = STORAGE_VERSION;
+        /// Position within the current migration step to continue migrating from
+        pub MigrationCursor get(migration_cursor): u32;
+        /// Maximum number of items migrated per block
+        pub MigrationBatchSize get(migration_batch_size) config(): u32;
         /// Nonce for creating unique hashes per user per withdraw request
         pub WithdrawNonceOf get(withdraw_nonce_of): map T::AccountId => u32;
     }
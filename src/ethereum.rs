@@ -0,0 +1,339 @@
+// Copyright 2018 Commonwealth Labs, Inc.
+// This file is part of Edgeware.
+
+// Edgeware is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Edgeware is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Edgeware.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verification of Ethereum transactions and receipts against the roots of a
+//! block, used to back deposits with proofs instead of authority signatures.
+
+use rstd::prelude::*;
+use primitives::{H160, H256, U256};
+use tiny_keccak;
+use secp256k1;
+use rlp::Rlp;
+
+/// Signature of the event the bridge contract emits when funds are locked
+/// for a transfer to Edgeware. The recipient is the Edgeware account id,
+/// left aligned and zero padded to 32 bytes.
+pub const LOCK_EVENT_SIGNATURE: &[u8] = b"Lock(address,bytes32,uint256)";
+
+/// Proof that a transaction and its receipt are included in an Ethereum block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ReceiptProof {
+    /// Hash of the block including the transaction
+    pub block_hash: H256,
+    /// Index of the transaction within the block
+    pub transaction_index: u64,
+    /// Trie nodes from the transactions root down to the transaction
+    pub transaction_proof: Vec<Vec<u8>>,
+    /// Trie nodes from the receipts root down to the receipt
+    pub receipt_proof: Vec<Vec<u8>>,
+}
+
+/// The fields of an Ethereum block header the bridge relies on.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Header {
+    pub parent_hash: H256,
+    pub number: u64,
+    pub timestamp: u64,
+    pub difficulty: U256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+}
+
+/// A log entry of a transaction receipt.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LogEntry {
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+/// Keccak-256 hash of the data, as used throughout Ethereum
+pub fn keccak256(data: &[u8]) -> H256 {
+    H256::from(tiny_keccak::keccak256(data))
+}
+
+/// Ethereum address of a secp256k1 public key
+pub fn public_to_address(public: &secp256k1::PublicKey) -> H160 {
+    H160::from_slice(&keccak256(&public.serialize()[1..])[12..])
+}
+
+/// Recover the Ethereum address that produced a 65-byte `r || s || v` ECDSA
+/// signature over a message hash
+pub fn recover_address(message: &H256, signature: &[u8]) -> Result<H160, &'static str> {
+    if signature.len() != 65 {
+        return Err("Invalid signature length");
+    }
+    let v = signature[64];
+    let recovery_id = secp256k1::RecoveryId::parse(if v >= 27 { v - 27 } else { v })
+        .map_err(|_| "Invalid signature recovery id")?;
+    let mut rs = [0u8; 64];
+    rs.copy_from_slice(&signature[..64]);
+    let mut msg = [0u8; 32];
+    msg.copy_from_slice(&message[..]);
+
+    let public = secp256k1::recover(&secp256k1::Message::parse(&msg), &secp256k1::Signature::parse(&rs), &recovery_id)
+        .map_err(|_| "Invalid signature")?;
+    Ok(public_to_address(&public))
+}
+
+/// A 32 byte big endian ABI word holding a `uint256`
+pub fn abi_uint(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+/// Hash of the withdraw message the bridge contract verifies authority signatures
+/// against, reproducible in Solidity as `keccak256(abi.encodePacked(uint256 chainId,
+/// address bridge, address recipient, uint256 amount, uint256 nonce, uint256 authoritySetId))`
+pub fn withdraw_message(chain_id: u64, bridge: &H160, recipient: &H160, amount: U256, nonce: u32, authority_set_id: u64) -> H256 {
+    let mut packed = Vec::with_capacity(4 * 32 + 2 * 20);
+    packed.extend_from_slice(&abi_uint(U256::from(chain_id)));
+    packed.extend_from_slice(&bridge[..]);
+    packed.extend_from_slice(&recipient[..]);
+    packed.extend_from_slice(&abi_uint(amount));
+    packed.extend_from_slice(&abi_uint(U256::from(nonce)));
+    packed.extend_from_slice(&abi_uint(U256::from(authority_set_id)));
+    keccak256(&packed)
+}
+
+/// EIP-712 type of the domain of withdraw approvals
+pub const EIP712_DOMAIN_TYPE: &[u8] = b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+/// EIP-712 type of withdraw approvals
+pub const WITHDRAW_TYPE: &[u8] = b"Withdraw(address recipient,uint256 amount,uint256 nonce,uint256 authoritySetId)";
+/// EIP-712 domain name of the bridge contract
+pub const DOMAIN_NAME: &[u8] = b"Edgeware Bridge";
+/// EIP-712 domain version of the bridge contract
+pub const DOMAIN_VERSION: &[u8] = b"1";
+
+fn abi_address(address: &H160) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(&address[..]);
+    word
+}
+
+/// EIP-712 domain separator of a bridge contract
+pub fn domain_separator(chain_id: u64, bridge: &H160) -> H256 {
+    let mut encoded = Vec::with_capacity(5 * 32);
+    encoded.extend_from_slice(&keccak256(EIP712_DOMAIN_TYPE)[..]);
+    encoded.extend_from_slice(&keccak256(DOMAIN_NAME)[..]);
+    encoded.extend_from_slice(&keccak256(DOMAIN_VERSION)[..]);
+    encoded.extend_from_slice(&abi_uint(U256::from(chain_id)));
+    encoded.extend_from_slice(&abi_address(bridge));
+    keccak256(&encoded)
+}
+
+/// EIP-712 struct hash of a withdraw approval
+pub fn withdraw_struct_hash(recipient: &H160, amount: U256, nonce: u32, authority_set_id: u64) -> H256 {
+    let mut encoded = Vec::with_capacity(5 * 32);
+    encoded.extend_from_slice(&keccak256(WITHDRAW_TYPE)[..]);
+    encoded.extend_from_slice(&abi_address(recipient));
+    encoded.extend_from_slice(&abi_uint(amount));
+    encoded.extend_from_slice(&abi_uint(U256::from(nonce)));
+    encoded.extend_from_slice(&abi_uint(U256::from(authority_set_id)));
+    keccak256(&encoded)
+}
+
+/// EIP-712 digest signed for a struct hash within a domain
+pub fn typed_data_hash(domain_separator: &H256, struct_hash: &H256) -> H256 {
+    let mut encoded = Vec::with_capacity(2 + 2 * 32);
+    encoded.extend_from_slice(&[0x19, 0x01]);
+    encoded.extend_from_slice(&domain_separator[..]);
+    encoded.extend_from_slice(&struct_hash[..]);
+    keccak256(&encoded)
+}
+
+/// Topic identifying the bridge contract's lock event
+pub fn lock_event_topic() -> H256 {
+    keccak256(LOCK_EVENT_SIGNATURE)
+}
+
+/// Decode a 32 byte big endian ABI word into a `uint256`
+pub fn decode_uint(word: &[u8]) -> Option<U256> {
+    if word.len() != 32 {
+        return None;
+    }
+    Some(U256::from_big_endian(word))
+}
+
+fn decode_hash(item: &Rlp) -> Result<H256, &'static str> {
+    let data = item.data()?;
+    if data.len() != 32 {
+        return Err("Invalid hash length");
+    }
+    Ok(H256::from_slice(data))
+}
+
+fn decode_u64(item: &Rlp) -> Result<u64, &'static str> {
+    let data = item.data()?;
+    if data.len() > 8 {
+        return Err("Invalid integer length");
+    }
+    Ok(data.iter().fold(0u64, |v, b| (v << 8) | *b as u64))
+}
+
+/// Decode an RLP encoded block header, returning its hash along with it
+pub fn decode_header(raw: &[u8]) -> Result<(H256, Header), &'static str> {
+    let fields = Rlp::new(raw)?.list()?;
+    if fields.len() < 15 {
+        return Err("Invalid header");
+    }
+    let difficulty = fields[7].data()?;
+    if difficulty.len() > 32 {
+        return Err("Invalid integer length");
+    }
+
+    let header = Header {
+        parent_hash: decode_hash(&fields[0])?,
+        number: decode_u64(&fields[8])?,
+        timestamp: decode_u64(&fields[11])?,
+        difficulty: U256::from_big_endian(difficulty),
+        transactions_root: decode_hash(&fields[4])?,
+        receipts_root: decode_hash(&fields[5])?,
+    };
+    Ok((keccak256(raw), header))
+}
+
+/// Whether a difficulty is within the bounds a block can adjust its parent's
+/// difficulty by: up by 2/2048ths with uncles, down by 99/2048ths. The difficulty
+/// bomb is not accounted for.
+pub fn is_valid_difficulty(parent: U256, difficulty: U256) -> bool {
+    let step = parent / U256::from(2048);
+    !difficulty.is_zero()
+        && difficulty <= parent + step * U256::from(2)
+        && difficulty + step * U256::from(99) >= parent
+}
+
+/// Decode the logs of a successful transaction receipt. Typed receipts are
+/// accepted as well as legacy ones.
+pub fn decode_receipt(raw: &[u8]) -> Result<Vec<LogEntry>, &'static str> {
+    let raw = match raw.first() {
+        Some(t) if *t < 0x7f => &raw[1..],
+        _ => raw,
+    };
+    let receipt = Rlp::new(raw)?.list()?;
+    if receipt.len() != 4 {
+        return Err("Invalid receipt");
+    }
+    if receipt[0].data()? != &[1u8][..] {
+        return Err("Receipt reports failed transaction");
+    }
+
+    receipt[3].list()?.iter().map(|log| {
+        let fields = log.list()?;
+        if fields.len() != 3 {
+            return Err("Invalid receipt log");
+        }
+        let address = fields[0].data()?;
+        if address.len() != 20 {
+            return Err("Invalid receipt log");
+        }
+        let topics = fields[1].list()?.iter().map(|t| {
+            let topic = t.data()?;
+            if topic.len() != 32 {
+                return Err("Invalid receipt log");
+            }
+            Ok(H256::from_slice(topic))
+        }).collect::<Result<Vec<_>, _>>()?;
+        Ok(LogEntry { address: H160::from_slice(address), topics, data: fields[2].data()?.to_vec() })
+    }).collect()
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+}
+
+/// Decode a hex-prefix encoded path, returning whether it ends in a leaf
+fn decode_path(encoded: &[u8]) -> Result<(bool, Vec<u8>), &'static str> {
+    let first = *encoded.first().ok_or("Invalid trie node path")?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err("Invalid trie node path");
+    }
+    let mut nibbles = if flag & 1 == 1 { vec![first & 0x0f] } else { vec![] };
+    nibbles.extend(to_nibbles(&encoded[1..]));
+    Ok((flag & 2 == 2, nibbles))
+}
+
+fn leaf_value(item: &Rlp) -> Result<Vec<u8>, &'static str> {
+    let value = item.data()?;
+    if value.is_empty() {
+        return Err("Key not found in trie");
+    }
+    Ok(value.to_vec())
+}
+
+/// Verify a Merkle-Patricia trie proof and return the value stored under `key`.
+/// Proof nodes are ordered from the root down; nodes embedded in their parent
+/// are not part of the proof.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Vec<u8>]) -> Result<Vec<u8>, &'static str> {
+    let mut nodes = proof.iter();
+    let mut node: &[u8] = nodes.next().ok_or("Incomplete trie proof")?;
+    if keccak256(node) != *root {
+        return Err("Invalid trie proof root");
+    }
+
+    let nibbles = to_nibbles(key);
+    let mut path = &nibbles[..];
+    loop {
+        let items = Rlp::new(node)?.list()?;
+        let child = match items.len() {
+            17 => {
+                if path.is_empty() {
+                    return leaf_value(&items[16]);
+                }
+                let child = items[path[0] as usize];
+                path = &path[1..];
+                child
+            },
+            2 => {
+                let (is_leaf, partial) = decode_path(items[0].data()?)?;
+                if is_leaf {
+                    if &partial[..] != path {
+                        return Err("Key not found in trie");
+                    }
+                    return leaf_value(&items[1]);
+                }
+                if !path.starts_with(&partial) {
+                    return Err("Key not found in trie");
+                }
+                path = &path[partial.len()..];
+                items[1]
+            },
+            _ => return Err("Invalid trie node"),
+        };
+
+        node = if child.is_list() {
+            child.as_raw()
+        } else {
+            let hash = child.data()?;
+            if hash.is_empty() {
+                return Err("Key not found in trie");
+            }
+            if hash.len() != 32 {
+                return Err("Invalid trie node");
+            }
+            let next = nodes.next().ok_or("Incomplete trie proof")?;
+            if keccak256(next) != H256::from_slice(hash) {
+                return Err("Invalid trie proof");
+            }
+            next
+        };
+    }
+}
@@ -46,20 +46,31 @@ extern crate srml_session as session;
 extern crate srml_timestamp as timestamp;
 extern crate srml_democracy as democracy;
 extern crate srml_consensus as consensus;
+extern crate tiny_keccak;
+extern crate sha2;
+extern crate secp256k1;
 
+pub mod rlp;
+pub mod ethereum;
+pub mod bitcoin;
+pub mod mmr;
 pub mod bridge;
-pub use bridge::{Module, Trait, RawEvent, Event};
+pub use bridge::{Module, Trait, RawEvent, Event, ChainId, AddressFormat, ForeignAddress, Status, Direction, RateLimit, ApprovalThreshold,
+    AuthoritySource, MemberSource, DepositProof, SignatureScheme, DepositRecord, WithdrawRecord};
 
 // Tests for Bridge Module
 #[cfg(test)]
 mod tests {
     use super::*;
+    use codec::Encode;
     use runtime_io::with_externalities;
     use system::{EventRecord, Phase};
-    use primitives::{H256, Blake2Hasher, Hasher};
-    use runtime_primitives::{BuildStorage};
-    use runtime_primitives::traits::{BlakeTwo256, Identity};
+    use primitives::{H160, H256, U256, Blake2Hasher, Hasher};
+    use runtime_primitives::{BuildStorage, Permill};
+    use runtime_primitives::traits::{BlakeTwo256, Hash, Identity, OnFinalise};
     use runtime_primitives::testing::{Digest, DigestItem, Header};
+    use democracy::VoteThreshold;
+    use runtime_support::{StorageValue, StorageMap};
 
     impl_outer_origin! {
         pub enum Origin for Test {}
@@ -145,30 +156,256 @@ mod tests {
         }.build_storage().unwrap().0);
         t.extend(bridge::GenesisConfig::<Test>{
             authorities: vec![1, 2, 3],
+            guardians: vec![6],
+            governance_authorities: vec![],
+            candidacy_bond: 50,
+            relayer_bond: 50,
+            unbonding_period: 10,
+            deposit_fee: Permill::from_percent(0),
+            withdraw_fee: Permill::from_percent(0),
+            bonus_signers: 0,
+            signer_bonus: Permill::from_percent(0),
+            cancel_threshold: Permill::from_percent(0),
+            record_retention: 0,
+            migration_batch_size: 2,
+            legacy_chain_id: ETH,
+            ..Default::default()
         }.build_storage().unwrap().0);
         t.into()
     }
 
+    const ETH: ChainId = 1;
+    const EVM: ChainId = 2;
+    // EIP-155 chain id of Ethereum, distinct from its id in the registry
+    const ETH_EVM_CHAIN_ID: u64 = 42;
+
+    // Registers the eligible blockchains used throughout the tests along with the
+    // EVM chain id and bridge contract of Ethereum, bonds equal
+    // stake for each bridge authority and registers its withdraw signing key.
+    fn setup() {
+        assert_ok!(Bridge::register_chain(Origin::ROOT, ETH, b"Ethereum".to_vec(), AddressFormat::Evm));
+        assert_ok!(Bridge::register_chain(Origin::ROOT, EVM, b"Other EVM".to_vec(), AddressFormat::Evm));
+        assert_ok!(Bridge::set_evm_chain_id(Origin::ROOT, ETH, ETH_EVM_CHAIN_ID));
+        assert_ok!(Bridge::set_bridge_contract(Origin::ROOT, ETH, bridge_contract()));
+        for authority in 1..4 {
+            assert_ok!(Bridge::bond(Origin::signed(authority), 1000));
+            assert_ok!(register_signing_key(authority));
+        }
+    }
+
+    fn bridge_contract() -> H160 {
+        H160::from([0x33u8; 20])
+    }
+
+    fn recipient() -> ForeignAddress {
+        ForeignAddress::Evm(H160::from([0x11u8; 20]))
+    }
+
+    fn trie_ref(node: &[u8]) -> Vec<u8> {
+        if node.len() < 32 { node.to_vec() } else { rlp::encode_bytes(&ethereum::keccak256(node)[..]) }
+    }
+
+    // Builds a trie of the entries of a block at indices 0 and 1, returning its
+    // root and the proof of the entry at index 0. Key 0x80 follows branch 8 into
+    // a leaf with path [0], key 0x01 follows branch 0 into a leaf with path [1].
+    fn block_trie(first: &[u8], second: &[u8]) -> (H256, Vec<Vec<u8>>) {
+        let first_leaf = rlp::encode_list(&[rlp::encode_bytes(&[0x30]), rlp::encode_bytes(first)]);
+        let second_leaf = rlp::encode_list(&[rlp::encode_bytes(&[0x31]), rlp::encode_bytes(second)]);
+        let mut children = vec![rlp::encode_bytes(&[]); 17];
+        children[0] = trie_ref(&second_leaf);
+        children[8] = trie_ref(&first_leaf);
+        let branch = rlp::encode_list(&children);
+
+        let mut proof = vec![branch.clone()];
+        if first_leaf.len() >= 32 {
+            proof.push(first_leaf);
+        }
+        (ethereum::keccak256(&branch), proof)
+    }
+
+    fn lock_receipt(contract: H160, target: u64, quantity: u64) -> Vec<u8> {
+        let mut recipient = [0u8; 32];
+        recipient[..8].copy_from_slice(&target.encode());
+        let mut amount = [0u8; 32];
+        for i in 0..8 {
+            amount[31 - i] = (quantity >> (8 * i)) as u8;
+        }
+        let log = rlp::encode_list(&[
+            rlp::encode_bytes(&contract[..]),
+            rlp::encode_list(&[
+                rlp::encode_bytes(&ethereum::lock_event_topic()[..]),
+                rlp::encode_bytes(&[0x22u8; 32]),
+                rlp::encode_bytes(&recipient),
+            ]),
+            rlp::encode_bytes(&amount),
+        ]);
+        rlp::encode_list(&[rlp::encode_bytes(&[1]), rlp::encode_u64(21000), rlp::encode_bytes(&[0u8; 256]), rlp::encode_list(&[log])])
+    }
+
+    // Builds the tries of a block with a lock of `quantity` for `target` at index 0,
+    // returning its transactions and receipts roots and the proof of the lock for
+    // a block hash yet to be filled in.
+    fn lock_block(target: u64, quantity: u64) -> (H256, H256, ethereum::ReceiptProof) {
+        let contract = bridge_contract();
+        let (transactions_root, transaction_proof) = block_trie(b"lock transaction", b"other transaction");
+        let (receipts_root, receipt_proof) = block_trie(&lock_receipt(contract, target, quantity), &lock_receipt(contract, 4, 1));
+
+        let proof = ethereum::ReceiptProof {
+            block_hash: H256::default(),
+            transaction_index: 0,
+            transaction_proof,
+            receipt_proof,
+        };
+        (transactions_root, receipts_root, proof)
+    }
+
+    // Checkpoints a block with a lock of `quantity` for `target` at index 0 and
+    // returns the hash of the locking transaction along with its proof.
+    fn setup_lock(target: u64, quantity: u64) -> (H256, DepositProof) {
+        let block_hash = H256::from([0x44u8; 32]);
+        let (transactions_root, receipts_root, mut proof) = lock_block(target, quantity);
+        assert_ok!(Bridge::set_block_roots(Origin::ROOT, ETH, block_hash, transactions_root, receipts_root));
+        proof.block_hash = block_hash;
+        (ethereum::keccak256(b"lock transaction"), DepositProof::EthereumReceipt(proof))
+    }
+
+    fn eth_header(parent: H256, number: u64, timestamp: u64, difficulty: u64, roots: (H256, H256)) -> Vec<u8> {
+        rlp::encode_list(&[
+            rlp::encode_bytes(&parent[..]),
+            rlp::encode_bytes(&[0u8; 32]),
+            rlp::encode_bytes(&[0u8; 20]),
+            rlp::encode_bytes(&[0u8; 32]),
+            rlp::encode_bytes(&(roots.0)[..]),
+            rlp::encode_bytes(&(roots.1)[..]),
+            rlp::encode_bytes(&[0u8; 256]),
+            rlp::encode_u64(difficulty),
+            rlp::encode_u64(number),
+            rlp::encode_u64(8_000_000),
+            rlp::encode_u64(0),
+            rlp::encode_u64(timestamp),
+            rlp::encode_bytes(&[]),
+            rlp::encode_bytes(&[0u8; 32]),
+            rlp::encode_bytes(&[0u8; 8]),
+        ])
+    }
+
+    const BTC: ChainId = 3;
+    const REGTEST_BITS: u32 = 0x207fffff;
+
+    fn le_bytes(value: u64, len: usize) -> Vec<u8> {
+        (0..len).map(|i| (value >> (8 * i)) as u8).collect()
+    }
+
+    fn federation_script() -> Vec<u8> {
+        let mut script = vec![0x00, 0x14];
+        script.extend_from_slice(&[0x77u8; 20]);
+        script
+    }
+
+    fn op_return(data: &[u8]) -> Vec<u8> {
+        let mut script = vec![0x6a, data.len() as u8];
+        script.extend_from_slice(data);
+        script
+    }
+
+    fn btc_transaction(outputs: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut tx = le_bytes(1, 4);
+        tx.push(1);
+        tx.extend_from_slice(&[0x99u8; 36]);
+        tx.push(0);
+        tx.extend_from_slice(&[0xffu8; 4]);
+        tx.push(outputs.len() as u8);
+        for &(value, ref script) in outputs {
+            tx.extend(le_bytes(value, 8));
+            tx.push(script.len() as u8);
+            tx.extend_from_slice(script);
+        }
+        tx.extend(le_bytes(0, 4));
+        tx
+    }
+
+    // Mines a header on top of `prev` whose hash meets the target of `bits`, or
+    // misses it when `valid` is false.
+    fn btc_header(prev: H256, merkle_root: H256, time: u32, bits: u32, valid: bool) -> Vec<u8> {
+        for nonce in 0u64.. {
+            let mut header = le_bytes(2, 4);
+            header.extend_from_slice(&prev[..]);
+            header.extend_from_slice(&merkle_root[..]);
+            header.extend(le_bytes(time as u64, 4));
+            header.extend(le_bytes(bits as u64, 4));
+            header.extend(le_bytes(nonce, 4));
+            if bitcoin::is_valid_pow(&bitcoin::sha256d(&header), bits) == valid {
+                return header;
+            }
+        }
+        unreachable!()
+    }
+
+    fn setup_bitcoin(checkpoint: &[u8], height: u32, period_start: u32) {
+        assert_ok!(Bridge::register_chain(Origin::ROOT, BTC, b"Bitcoin".to_vec(), AddressFormat::Bitcoin));
+        assert_ok!(Bridge::set_federation_script(Origin::ROOT, BTC, federation_script()));
+        assert_ok!(Bridge::set_bitcoin_checkpoint(Origin::ROOT, BTC, checkpoint.to_vec(), height, U256::from(1000), period_start, REGTEST_BITS));
+    }
+
+    fn last_event() -> Event {
+        System::events().pop().expect("Event expected").event
+    }
+
     fn deposit(who: u64, target: u64, transaction_hash: H256, quantity: u64) -> runtime_support::dispatch::Result {
-        Bridge::deposit(Origin::signed(who), target, transaction_hash, quantity)
+        Bridge::deposit(Origin::signed(who), ETH, target, transaction_hash, quantity, None)
     }
 
     fn sign_deposit(who: u64, target: u64, transaction_hash: H256, quantity: u64) -> runtime_support::dispatch::Result {
-        Bridge::sign_deposit(Origin::signed(who), target, transaction_hash, quantity)
+        Bridge::sign_deposit(Origin::signed(who), ETH, target, transaction_hash, quantity)
     }
 
     fn withdraw(who: u64, quantity: u64, signed_cross_chain_tx: &[u8]) -> runtime_support::dispatch::Result {
-        Bridge::withdraw(Origin::signed(who), quantity, signed_cross_chain_tx.to_vec())
+        Bridge::withdraw(Origin::signed(who), ETH, recipient(), quantity, signed_cross_chain_tx.to_vec())
+    }
+
+    fn signing_key(who: u64) -> secp256k1::SecretKey {
+        secp256k1::SecretKey::parse(&[who as u8; 32]).unwrap()
+    }
+
+    fn signing_address(who: u64) -> H160 {
+        ethereum::public_to_address(&secp256k1::PublicKey::from_secret_key(&signing_key(who)))
+    }
+
+    fn sign_message(who: u64, message: H256) -> Vec<u8> {
+        let mut msg = [0u8; 32];
+        msg.copy_from_slice(&message[..]);
+        let (signature, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&msg), &signing_key(who)).unwrap();
+        let mut signed = signature.serialize().to_vec();
+        signed.push(recovery_id.serialize() + 27);
+        signed
+    }
+
+    fn withdraw_evidence(who: u64, scheme: SignatureScheme, recipient: H160, amount: u64, nonce: u32, set_id: u64) -> (SignatureScheme, H160, U256, Vec<u8>) {
+        let message = match scheme {
+            SignatureScheme::Raw => ethereum::withdraw_message(ETH_EVM_CHAIN_ID, &bridge_contract(), &recipient, U256::from(amount), nonce, set_id),
+            SignatureScheme::Eip712 => ethereum::typed_data_hash(
+                &ethereum::domain_separator(ETH_EVM_CHAIN_ID, &bridge_contract()),
+                &ethereum::withdraw_struct_hash(&recipient, U256::from(amount), nonce, set_id)
+            ),
+        };
+        (scheme, recipient, U256::from(amount), sign_message(who, message))
+    }
+
+    fn register_signing_key(who: u64) -> runtime_support::dispatch::Result {
+        let proof = sign_message(who, Bridge::signing_key_proof_message(&who));
+        Bridge::register_signing_key(Origin::signed(who), signing_address(who), proof)
     }
 
-    fn sign_withdraw(who: u64, target: u64, record_hash: H256, quantity: u64, signed_cross_chain_tx: &[u8]) -> runtime_support::dispatch::Result {
-        Bridge::sign_withdraw(Origin::signed(who), target, record_hash, quantity, signed_cross_chain_tx.to_vec())
+    fn sign_withdraw(who: u64, target: u64, record_hash: H256, quantity: u64) -> runtime_support::dispatch::Result {
+        let message = Bridge::withdraw_message(record_hash).unwrap_or_default();
+        Bridge::sign_withdraw(Origin::signed(who), target, record_hash, quantity, recipient(), SignatureScheme::Raw, sign_message(who, message))
     }
 
     #[test]
     fn params_should_be_set_correctly() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             assert_eq!(Balances::total_balance(&1), 10000);
             assert_eq!(Balances::total_balance(&2), 10000);
             assert_eq!(Balances::total_balance(&3), 10000);
@@ -183,14 +420,13 @@ mod tests {
     fn deposit_as_a_function_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             assert_ok!(deposit(5, 5, hash, 10));
-            assert_eq!(System::events(), vec![
-                EventRecord {
-                    phase: Phase::ApplyExtrinsic(0),
-                    event: Event::bridge(RawEvent::Deposit(5, hash, 10)),
-                }]
-            );
+            assert_eq!(System::events().pop(), Some(EventRecord {
+                phase: Phase::ApplyExtrinsic(0),
+                event: Event::bridge(RawEvent::Deposit(ETH, 5, hash, 10)),
+            }));
         });
     }
 
@@ -198,6 +434,7 @@ mod tests {
     fn deposit_with_same_tx_twice_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -205,10 +442,75 @@ mod tests {
         });
     }
 
+    #[test]
+    fn deposit_with_same_tx_on_different_chains_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(Bridge::deposit(Origin::signed(5), EVM, 5, hash, quantity, None));
+            assert_eq!(Bridge::deposit_by_index(0), Some((ETH, hash)));
+            assert_eq!(Bridge::deposit_by_index(1), Some((EVM, hash)));
+            assert_eq!(Bridge::deposit_by_index(2), None);
+            assert_eq!(Bridge::deposit_of((EVM, hash)), Some(DepositRecord {
+                index: 1,
+                chain_id: EVM,
+                target: 5,
+                quantity,
+                signers: vec![],
+                status: Status::Pending,
+                created_at: 1,
+                authority_set_id: 0,
+            }));
+            assert_eq!(last_event(), Event::bridge(RawEvent::Deposit(EVM, 5, hash, quantity)));
+        });
+    }
+
+    #[test]
+    fn deposit_on_unregistered_chain_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_eq!(deposit(5, 5, hash, 10), Err("Chain is not registered"));
+        });
+    }
+
+    #[test]
+    fn register_chain_as_non_root_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert!(Bridge::register_chain(Origin::signed(1), ETH, b"Ethereum".to_vec(), AddressFormat::Evm).is_err());
+            setup();
+            assert_eq!(Bridge::chain_ids(), vec![ETH, EVM]);
+            assert_eq!(Bridge::register_chain(Origin::ROOT, ETH, b"Ethereum".to_vec(), AddressFormat::Evm), Err("Chain already registered"));
+        });
+    }
+
+    #[test]
+    fn requests_on_disabled_chain_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(Bridge::set_chain_enabled(Origin::ROOT, ETH, false));
+            assert_eq!(last_event(), Event::bridge(RawEvent::ChainStatusChanged(ETH, false)));
+            assert_eq!(deposit(5, 5, Blake2Hasher::hash(b"c sends money to d"), 10), Err("Chain is disabled"));
+            assert_eq!(withdraw(5, 10, b"a sent b 1 ETH"), Err("Chain is disabled"));
+            // Pending records can still be signed off
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_ok!(Bridge::set_chain_enabled(Origin::ROOT, ETH, true));
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+        });
+    }
+
     #[test]
     fn sign_deposit_as_bridge_authority_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -220,6 +522,7 @@ mod tests {
     fn sign_deposit_supermajority_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -235,6 +538,7 @@ mod tests {
     fn sign_deposit_once_complete_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -252,6 +556,7 @@ mod tests {
     fn sign_non_existent_deposit_as_bridge_authority_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_eq!(sign_deposit(1, 5, hash, quantity), Err("Invalid transaction hash"));
@@ -262,6 +567,7 @@ mod tests {
     fn sign_deposit_with_wrong_quantity_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -273,6 +579,7 @@ mod tests {
     fn sign_deposit_with_wrong_target_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -284,6 +591,7 @@ mod tests {
     fn sign_deposit_as_non_authority_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -295,6 +603,7 @@ mod tests {
     fn sign_deposit_twice_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let hash = Blake2Hasher::hash(b"a sends money to b");
             let quantity = 10;
             assert_ok!(deposit(5, 5, hash, quantity));
@@ -307,14 +616,13 @@ mod tests {
     fn withdraw_as_a_function_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let signed_tx = b"a sends money to b on Ethereum";
             assert_ok!(withdraw(5, 10, signed_tx));
-            assert_eq!(System::events(), vec![
-                EventRecord {
-                    phase: Phase::ApplyExtrinsic(0),
-                    event: Event::bridge(RawEvent::Withdraw(5, 10)),
-                }]
-            );
+            assert_eq!(System::events().pop(), Some(EventRecord {
+                phase: Phase::ApplyExtrinsic(0),
+                event: Event::bridge(RawEvent::Withdraw(ETH, 5, recipient(), 10)),
+            }));
         });
     }
 
@@ -322,25 +630,70 @@ mod tests {
     fn withdraw_with_not_enough_balance_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let signed_tx = b"a sends money to b on Ethereum";
             assert_eq!(Balances::total_balance(&4), 100);
             assert_eq!(withdraw(4, 101, signed_tx), Err("Invalid balance for withdraw"));
         });
     }
 
+    #[test]
+    fn withdraw_should_reserve_balance() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let signed_tx = b"a sends money to b on Ethereum";
+            assert_ok!(withdraw(4, 60, signed_tx));
+            assert_eq!(Balances::free_balance(&4), 40);
+            assert_eq!(Balances::reserved_balance(&4), 60);
+            // Reserved funds can't back a second withdraw
+            assert_eq!(withdraw(4, 60, signed_tx), Err("Invalid balance for withdraw"));
+            assert_ok!(withdraw(4, 40, signed_tx));
+            assert_eq!(Balances::free_balance(&4), 0);
+            assert_eq!(Balances::reserved_balance(&4), 100);
+        });
+    }
+
+    #[test]
+    fn withdraw_to_zero_address_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let zero = ForeignAddress::Evm(H160::zero());
+            assert_eq!(Bridge::withdraw(Origin::signed(5), ETH, zero, 10, vec![]), Err("Invalid zero recipient address"));
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_with_wrong_recipient_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let cross_chain_proof = b"a sent b 1 ETH";
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            let other = ForeignAddress::Evm(H160::from([0x22u8; 20]));
+            assert_eq!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, quantity, other, SignatureScheme::Raw, cross_chain_proof.to_vec()),
+                       Err("Recipients do not match"));
+        });
+    }
+
     #[test]
     fn sign_withdraw_supermajority_should_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
             assert_eq!(Balances::total_balance(&5), 100);
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, quantity));
             assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+            assert_ok!(sign_withdraw(2, 5, hash, quantity));
             assert_eq!(Balances::total_balance(&5), 100 - quantity);
+            assert_eq!(Balances::reserved_balance(&5), 0);
         });
     }
 
@@ -348,16 +701,17 @@ mod tests {
     fn sign_withdraw_once_complete_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
             assert_eq!(Balances::total_balance(&5), 100);
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, quantity));
             assert_eq!(Balances::total_balance(&5), 100);
-            assert_ok!(sign_withdraw(2, 5, hash, quantity, cross_chain_proof));
+            assert_ok!(sign_withdraw(2, 5, hash, quantity));
             assert_eq!(Balances::total_balance(&5), 100 - quantity);
-            assert_eq!(sign_withdraw(3, 5, hash, quantity, cross_chain_proof),
+            assert_eq!(sign_withdraw(3, 5, hash, quantity),
                        Err("Transaction already completed"))
         });
     }
@@ -366,11 +720,12 @@ mod tests {
     fn sign_withdraw_with_wrong_quantity_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(1, 5, hash, quantity - 1, cross_chain_proof), Err("Quantities don't match"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(sign_withdraw(1, 5, hash, quantity - 1), Err("Quantities don't match"));
         });
     }
 
@@ -378,11 +733,12 @@ mod tests {
     fn sign_withdraw_with_wrong_target_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Accounts do not match"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(sign_withdraw(1, 4, hash, quantity), Err("Accounts do not match"));
         });
     }
 
@@ -390,11 +746,12 @@ mod tests {
     fn sign_withdraw_as_non_authority_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_eq!(sign_withdraw(5, 5, hash, quantity, cross_chain_proof), Err("Invalid non-authority sender"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(sign_withdraw(5, 5, hash, quantity), Err("Invalid non-authority sender"));
         });
     }
 
@@ -402,12 +759,13 @@ mod tests {
     fn sign_withdraw_twice_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
+            setup();
             let cross_chain_proof = b"a sent b 1 ETH";
             let quantity = 10;
             assert_ok!(withdraw(5, quantity, cross_chain_proof));
-            let hash = Bridge::withdraw_record_hash(0);
-            assert_ok!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof));
-            assert_eq!(sign_withdraw(1, 5, hash, quantity, cross_chain_proof), Err("Invalid duplicate signings"))
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, quantity));
+            assert_eq!(sign_withdraw(1, 5, hash, quantity), Err("Invalid duplicate signings"))
         });
     }
 
@@ -415,10 +773,1506 @@ mod tests {
     fn sign_withdraw_with_non_existent_record_hash_should_not_work() {
         with_externalities(&mut new_test_ext(), || {
             System::set_block_number(1);
-            let cross_chain_proof = b"a sent b 1 ETH";
+            setup();
             let quantity = 10;
             let hash = Blake2Hasher::hash(b"drew stone was here");
-            assert_eq!(sign_withdraw(1, 4, hash, quantity, cross_chain_proof), Err("Invalid record hash"));
+            assert_eq!(sign_withdraw(1, 4, hash, quantity), Err("Invalid record hash"));
+        });
+    }
+
+    #[test]
+    fn bond_should_reserve_stake() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_ok!(Bridge::bond(Origin::signed(1), 1000));
+            assert_ok!(Bridge::bond(Origin::signed(1), 500));
+            assert_eq!(Bridge::bonded(1), 1500);
+            assert_eq!(Balances::reserved_balance(&1), 1500);
+            assert_eq!(Bridge::total_authority_stake(), 1500);
+            assert_eq!(last_event(), Event::bridge(RawEvent::StakeBonded(1, 500)));
+            assert_eq!(Bridge::bond(Origin::signed(4), 101), Err("Invalid balance for bond"));
+        });
+    }
+
+    #[test]
+    fn unbond_and_withdraw_unbonded_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_ok!(Bridge::bond(Origin::signed(1), 1000));
+            assert_eq!(Bridge::unbond(Origin::signed(1), 1001), Err("Invalid unbond exceeding bonded stake"));
+            assert_ok!(Bridge::unbond(Origin::signed(1), 400));
+            assert_eq!(last_event(), Event::bridge(RawEvent::StakeUnbonded(1, 400, 11)));
+            assert_eq!(Bridge::bonded(1), 600);
+            assert_eq!(Bridge::unbonding(1), vec![(400, 11)]);
+            assert_eq!(Bridge::withdraw_unbonded(Origin::signed(1)), Err("No unbonded stake to withdraw"));
+            System::set_block_number(11);
+            assert_ok!(Bridge::withdraw_unbonded(Origin::signed(1)));
+            assert_eq!(Balances::reserved_balance(&1), 600);
+            assert_eq!(Bridge::unbonding(1), vec![]);
+            assert_eq!(last_event(), Event::bridge(RawEvent::UnbondedWithdrawn(1, 400)));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_should_only_weigh_bonded_authority_stake() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            // A bonded non-authority doesn't add to the authority stake
+            assert_ok!(Bridge::bond(Origin::signed(4), 100));
+            assert_eq!(Bridge::total_authority_stake(), 3000);
+            // Unbonded stake no longer weighs a signature
+            assert_ok!(Bridge::unbond(Origin::signed(1), 1000));
+            assert_eq!(Bridge::total_authority_stake(), 2000);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            let quantity = 10;
+            assert_ok!(deposit(5, 5, hash, quantity));
+            assert_ok!(sign_deposit(1, 5, hash, quantity));
+            assert_ok!(sign_deposit(2, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(3, 5, hash, quantity));
+            assert_eq!(Balances::total_balance(&5), 100 + quantity);
+        });
+    }
+
+    #[test]
+    fn report_conflicting_withdraw_should_slash_authority() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let quantity = 10;
+            assert_ok!(withdraw(5, quantity, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(Bridge::unbond(Origin::signed(1), 400));
+            let first = withdraw_evidence(1, SignatureScheme::Raw, H160::from([0x11u8; 20]), 10, 0, 0);
+            let second = withdraw_evidence(1, SignatureScheme::Eip712, H160::from([0x22u8; 20]), 10, 0, 0);
+            // Approvals for a contract the chain was configured with stay reportable
+            assert_ok!(Bridge::set_bridge_contract(Origin::ROOT, ETH, H160::from([0x44u8; 20])));
+            assert_ok!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first.clone(), second.clone()));
+            assert_eq!(last_event(), Event::bridge(RawEvent::Slashed(1, 1000)));
+            assert_eq!(Bridge::bonded(1), 0);
+            assert_eq!(Bridge::unbonding(1), vec![]);
+            assert_eq!(Balances::total_balance(&1), 9000);
+            assert_eq!(Bridge::authorities(), vec![2, 3]);
+            assert_eq!(Bridge::authority_quorum(), 2);
+            assert_eq!(sign_withdraw(1, 5, hash, quantity), Err("Invalid non-authority sender"));
+
+            // The same offence can't be reported twice
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first, second),
+                Err("Evidence already processed"));
+        });
+    }
+
+    #[test]
+    fn report_conflicting_withdraw_should_remove_unbonded_authority() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::unbond(Origin::signed(1), 1000));
+            System::set_block_number(11);
+            assert_ok!(Bridge::withdraw_unbonded(Origin::signed(1)));
+            let first = withdraw_evidence(1, SignatureScheme::Raw, H160::from([0x11u8; 20]), 10, 0, 0);
+            let second = withdraw_evidence(1, SignatureScheme::Raw, H160::from([0x22u8; 20]), 10, 0, 0);
+            assert_ok!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first.clone(), second.clone()));
+            assert_eq!(last_event(), Event::bridge(RawEvent::Slashed(1, 0)));
+            assert_eq!(Bridge::authorities(), vec![2, 3]);
+            assert_eq!(Bridge::authority_quorum(), 2);
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first, second),
+                Err("Evidence already processed"));
+        });
+    }
+
+    #[test]
+    fn report_conflicting_withdraw_with_invalid_evidence_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let first = withdraw_evidence(1, SignatureScheme::Raw, H160::from([0x11u8; 20]), 10, 0, 0);
+            let same = withdraw_evidence(1, SignatureScheme::Eip712, H160::from([0x11u8; 20]), 10, 0, 0);
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, H160::from([0x44u8; 20]), 0, 0, first.clone(), same.clone()),
+                Err("Unknown bridge contract"));
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first.clone(), same),
+                Err("Withdraws do not conflict"));
+
+            // Signed by another authority
+            let second = withdraw_evidence(2, SignatureScheme::Raw, H160::from([0x22u8; 20]), 10, 0, 0);
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first.clone(), second),
+                Err("Invalid evidence signature"));
+
+            // Signed for another nonce
+            let second = withdraw_evidence(1, SignatureScheme::Raw, H160::from([0x22u8; 20]), 10, 1, 0);
+            assert_eq!(Bridge::report_conflicting_withdraw(Origin::signed(5), 1, ETH, ETH_EVM_CHAIN_ID, bridge_contract(), 0, 0, first, second),
+                Err("Invalid evidence signature"));
+            assert_eq!(Bridge::bonded(1), 1000);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn report_fraudulent_deposit_should_slash_signers() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a never sent money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert!(Bridge::report_fraudulent_deposit(Origin::signed(2), ETH, hash).is_err());
+            assert_ok!(Bridge::report_fraudulent_deposit(Origin::ROOT, ETH, hash));
+            assert_eq!(last_event(), Event::bridge(RawEvent::Slashed(1, 1000)));
+            assert_eq!(Bridge::deposit_of((ETH, hash)).unwrap().status, Status::Rejected);
+            assert_eq!(sign_deposit(2, 5, hash, 10), Err("Transaction rejected"));
+            assert_eq!(Bridge::authorities(), vec![2, 3]);
+            assert_eq!(Bridge::bonded(2), 1000);
+
+            // Signers are not slashed again for the same deposit once they bond again
+            assert_ok!(Bridge::bond(Origin::signed(1), 500));
+            assert_eq!(Bridge::report_fraudulent_deposit(Origin::ROOT, ETH, hash), Err("Deposit already reported"));
+            assert_eq!(Bridge::bonded(1), 500);
+        });
+    }
+
+    #[test]
+    fn deposit_fee_should_reward_signers() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_fees(Origin::ROOT, Permill::from_percent(10), Permill::from_percent(0), 1, Permill::from_percent(50)));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 100));
+            assert_ok!(sign_deposit(1, 5, hash, 100));
+            assert_ok!(sign_deposit(2, 5, hash, 100));
+            assert_eq!(Balances::total_balance(&5), 190);
+            // The first signer receives the bonus half of the fee on top of an equal share
+            assert_eq!(Bridge::rewards(1), 7);
+            assert_eq!(Bridge::rewards(2), 2);
+            assert_eq!(Bridge::rewards(3), 0);
+        });
+    }
+
+    #[test]
+    fn withdraw_fee_should_reward_signers() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_fees(Origin::ROOT, Permill::from_percent(0), Permill::from_percent(10), 0, Permill::from_percent(0)));
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 100, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            // Signers approve the release of the quantity less their fee
+            assert_eq!(Bridge::withdraw_message(hash), Some(ethereum::withdraw_message(ETH_EVM_CHAIN_ID, &bridge_contract(), &H160::from([0x11u8; 20]), U256::from(90), 0, 0)));
+            assert_ok!(Bridge::set_fees(Origin::ROOT, Permill::from_percent(0), Permill::from_percent(20), 0, Permill::from_percent(0)));
+            assert_ok!(sign_withdraw(1, 5, hash, 100));
+            assert_ok!(sign_withdraw(2, 5, hash, 100));
+            assert_eq!(Balances::total_balance(&5), 0);
+            assert_eq!(Bridge::rewards(1), 5);
+            assert_eq!(Bridge::rewards(2), 5);
+        });
+    }
+
+    #[test]
+    fn claim_rewards_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_eq!(Bridge::claim_rewards(Origin::signed(1)), Err("No rewards to claim"));
+            assert_ok!(Bridge::set_fees(Origin::ROOT, Permill::from_percent(10), Permill::from_percent(0), 0, Permill::from_percent(0)));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 100));
+            assert_ok!(sign_deposit(1, 5, hash, 100));
+            assert_ok!(sign_deposit(2, 5, hash, 100));
+            assert_ok!(Bridge::claim_rewards(Origin::signed(1)));
+            assert_eq!(last_event(), Event::bridge(RawEvent::RewardsClaimed(1, 5)));
+            assert_eq!(Balances::free_balance(&1), 9005);
+            assert_eq!(Bridge::rewards(1), 0);
+        });
+    }
+
+    #[test]
+    fn expire_deposit_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(Bridge::set_chain_timeout(Origin::ROOT, ETH, 10));
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::expire_deposit(Origin::signed(6), ETH, hash), Err("Transaction has not timed out"));
+            System::set_block_number(11);
+            assert_eq!(sign_deposit(2, 5, hash, 10), Err("Transaction expired"));
+            assert_ok!(Bridge::expire_deposit(Origin::signed(6), ETH, hash));
+            assert_eq!(last_event(), Event::bridge(RawEvent::DepositExpired(ETH, hash)));
+            assert_eq!(Bridge::deposit_of((ETH, hash)), Some(DepositRecord {
+                index: 0,
+                chain_id: ETH,
+                target: 5,
+                quantity: 10,
+                signers: vec![1],
+                status: Status::Expired,
+                created_at: 1,
+                authority_set_id: 0,
+            }));
+            assert_eq!(Bridge::expire_deposit(Origin::signed(6), ETH, hash), Err("Transaction no longer pending"));
+            assert_eq!(Balances::total_balance(&5), 100);
+        });
+    }
+
+    #[test]
+    fn expire_withdraw_should_unreserve_funds() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(Bridge::set_chain_timeout(Origin::ROOT, ETH, 10));
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            assert_eq!(Balances::reserved_balance(&5), 10);
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            System::set_block_number(11);
+            assert_ok!(Bridge::expire_withdraw(Origin::signed(6), hash));
+            assert_eq!(last_event(), Event::bridge(RawEvent::WithdrawExpired(ETH, hash)));
+            assert_eq!(Balances::reserved_balance(&5), 0);
+            assert_eq!(Balances::free_balance(&5), 100);
+            assert_eq!(sign_withdraw(1, 5, hash, 10), Err("Transaction expired"));
+        });
+    }
+
+    #[test]
+    fn records_without_timeout_should_not_expire() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            System::set_block_number(1000);
+            assert_eq!(Bridge::expire_deposit(Origin::signed(6), ETH, hash), Err("Transaction has not timed out"));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+        });
+    }
+
+    #[test]
+    fn cancel_unsigned_withdraw_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(Bridge::withdraw_record_hash(1), None);
+            assert_eq!(Bridge::cancel_withdraw(Origin::signed(4), hash), Err("Invalid non-requester sender"));
+            assert_ok!(Bridge::cancel_withdraw(Origin::signed(5), hash));
+            assert_eq!(last_event(), Event::bridge(RawEvent::WithdrawCancelled(ETH, 5, hash)));
+            assert_eq!(Balances::free_balance(&5), 100);
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().status, Status::Cancelled);
+            assert_eq!(Bridge::cancel_withdraw(Origin::signed(5), hash), Err("Transaction no longer pending"));
+            assert_eq!(sign_withdraw(1, 5, hash, 10), Err("Transaction cancelled"));
+        });
+    }
+
+    #[test]
+    fn cancel_signed_withdraw_should_respect_cancel_threshold() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, 10));
+            assert_eq!(Bridge::cancel_withdraw(Origin::signed(5), hash), Err("Withdraw already signed by too much stake"));
+            // One of three equal authorities holds 2/3 of the stake needed for approval
+            assert_ok!(Bridge::set_cancel_threshold(Origin::ROOT, Permill::from_percent(70)));
+            assert_ok!(Bridge::cancel_withdraw(Origin::signed(5), hash));
+            assert_eq!(Balances::reserved_balance(&5), 0);
+        });
+    }
+
+    #[test]
+    fn cancel_threshold_should_count_signers_of_the_record_authority_set() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_handoff_window(Origin::ROOT, ETH, 10));
+            assert_ok!(Bridge::set_cancel_threshold(Origin::ROOT, Permill::from_percent(60)));
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, 10));
+            rotate_authorities(&[2, 3, 4]);
+
+            // The retired signer holds 1000 of the 1501 stake its set needs
+            assert_eq!(Bridge::cancel_withdraw(Origin::signed(5), hash), Err("Withdraw already signed by too much stake"));
+
+            // Once the retired set can no longer approve, only the current set counts
+            System::set_block_number(11);
+            assert_ok!(Bridge::cancel_withdraw(Origin::signed(5), hash));
+        });
+    }
+
+    #[test]
+    fn cancel_approved_withdraw_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, 10));
+            assert_ok!(sign_withdraw(2, 5, hash, 10));
+            assert_eq!(Bridge::cancel_withdraw(Origin::signed(5), hash), Err("Transaction no longer pending"));
+        });
+    }
+
+    #[test]
+    fn paused_deposits_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(Bridge::set_paused(Origin::signed(6), ETH, Direction::Deposit, true));
+            assert_eq!(last_event(), Event::bridge(RawEvent::PauseChanged(ETH, Direction::Deposit, true)));
+            assert_eq!(sign_deposit(1, 5, hash, 10), Err("Bridge is paused for this chain and direction"));
+            assert_eq!(deposit(5, 5, Blake2Hasher::hash(b"c sends money to d"), 10), Err("Bridge is paused for this chain and direction"));
+            // Other chains and directions are unaffected
+            assert_ok!(Bridge::deposit(Origin::signed(5), EVM, 5, hash, 10, None));
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            assert_ok!(Bridge::set_paused(Origin::ROOT, ETH, Direction::Deposit, false));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+        });
+    }
+
+    #[test]
+    fn paused_withdraws_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(Bridge::set_paused(Origin::ROOT, ETH, Direction::Withdraw, true));
+            assert_eq!(withdraw(5, 10, cross_chain_proof), Err("Bridge is paused for this chain and direction"));
+            assert_eq!(sign_withdraw(1, 5, hash, 10), Err("Bridge is paused for this chain and direction"));
+            // Requesters can still back out while paused
+            assert_ok!(Bridge::cancel_withdraw(Origin::signed(5), hash));
+        });
+    }
+
+    #[test]
+    fn set_paused_should_record_history() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_eq!(Bridge::set_paused(Origin::signed(5), ETH, Direction::Deposit, true), Err("Invalid non-guardian sender"));
+            assert_ok!(Bridge::set_paused(Origin::signed(6), ETH, Direction::Deposit, true));
+            assert_eq!(Bridge::set_paused(Origin::signed(6), ETH, Direction::Deposit, true), Err("Pause state unchanged"));
+            System::set_block_number(5);
+            assert_ok!(Bridge::set_paused(Origin::signed(6), ETH, Direction::Deposit, false));
+            System::set_block_number(8);
+            assert_ok!(Bridge::set_paused(Origin::ROOT, ETH, Direction::Deposit, true));
+            assert_eq!(Bridge::pause_history((ETH, Direction::Deposit)), vec![(1, Some(5)), (8, None)]);
+            assert_eq!(Bridge::pause_history((ETH, Direction::Withdraw)), vec![]);
+        });
+    }
+
+    fn set_rate_limit(direction: Direction) {
+        assert_ok!(Bridge::set_rate_limit(Origin::ROOT, ETH, direction, Some(RateLimit {
+            window: 10,
+            max_volume: 100,
+            max_single: 80,
+        })));
+    }
+
+    #[test]
+    fn deposit_above_single_limit_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            set_rate_limit(Direction::Deposit);
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_eq!(deposit(5, 5, hash, 81), Err("Quantity exceeds maximum single transfer"));
+            assert_ok!(deposit(5, 5, hash, 80));
+            assert_eq!(Bridge::remaining_capacity(ETH, Direction::Deposit), Some(100));
+            assert_eq!(Bridge::remaining_capacity(ETH, Direction::Withdraw), None);
+        });
+    }
+
+    #[test]
+    fn deposits_beyond_window_volume_should_be_queued() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            set_rate_limit(Direction::Deposit);
+            let first = Blake2Hasher::hash(b"a sends money to b");
+            let second = Blake2Hasher::hash(b"c sends money to d");
+            assert_ok!(deposit(5, 5, first, 80));
+            assert_ok!(sign_deposit(1, 5, first, 80));
+            assert_ok!(sign_deposit(2, 5, first, 80));
+            assert_eq!(Balances::total_balance(&5), 180);
+            assert_eq!(Bridge::remaining_capacity(ETH, Direction::Deposit), Some(20));
+
+            System::set_block_number(5);
+            assert_ok!(deposit(5, 5, second, 50));
+            assert_ok!(sign_deposit(1, 5, second, 50));
+            assert_ok!(sign_deposit(2, 5, second, 50));
+            assert_eq!(last_event(), Event::bridge(RawEvent::Queued(ETH, Direction::Deposit, second)));
+            assert_eq!(Bridge::queued((ETH, Direction::Deposit)), vec![second]);
+            assert_eq!(Bridge::deposit_of((ETH, second)).unwrap().status, Status::Approved);
+            assert_eq!(sign_deposit(3, 5, second, 50), Err("Transaction already approved"));
+            assert_eq!(Balances::total_balance(&5), 180);
+
+            // The queue stays put while the first deposit is within the window
+            Bridge::on_finalise(5);
+            assert_eq!(Balances::total_balance(&5), 180);
+
+            System::set_block_number(11);
+            Bridge::on_finalise(11);
+            assert_eq!(Balances::total_balance(&5), 230);
+            assert_eq!(Bridge::queued((ETH, Direction::Deposit)), vec![]);
+            assert_eq!(Bridge::deposit_of((ETH, second)).unwrap().status, Status::Executed);
+            assert_eq!(Bridge::remaining_capacity(ETH, Direction::Deposit), Some(50));
+        });
+    }
+
+    #[test]
+    fn withdraws_beyond_window_volume_should_be_queued() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            set_rate_limit(Direction::Withdraw);
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(Bridge::withdraw(Origin::signed(1), ETH, recipient(), 80, cross_chain_proof.to_vec()));
+            let first = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 1, first, 80));
+            assert_ok!(sign_withdraw(2, 1, first, 80));
+            assert_ok!(Bridge::withdraw(Origin::signed(1), ETH, recipient(), 80, cross_chain_proof.to_vec()));
+            let second = Bridge::withdraw_record_hash(1).unwrap();
+            assert_ok!(sign_withdraw(1, 1, second, 80));
+            assert_ok!(sign_withdraw(2, 1, second, 80));
+            assert_eq!(Bridge::queued((ETH, Direction::Withdraw)), vec![second]);
+            // Queued funds stay reserved until execution
+            assert_eq!(Balances::reserved_balance(&1), 1080);
+
+            System::set_block_number(11);
+            Bridge::on_finalise(11);
+            assert_eq!(Balances::reserved_balance(&1), 1000);
+            assert_eq!(Bridge::withdraw_of(second).unwrap().status, Status::Executed);
+        });
+    }
+
+    #[test]
+    fn set_threshold_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_eq!(Bridge::threshold((ETH, Direction::Deposit)), ApprovalThreshold::Vote(VoteThreshold::SuperMajorityApprove));
+            assert!(Bridge::set_threshold(Origin::signed(1), ETH, Direction::Deposit, ApprovalThreshold::Count(3)).is_err());
+            assert_eq!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Deposit, ApprovalThreshold::Count(0)), Err("Invalid zero signer count"));
+            assert_eq!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Deposit, ApprovalThreshold::StakeFraction(3, 2)), Err("Invalid stake fraction"));
+            assert_ok!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Deposit, ApprovalThreshold::Count(3)));
+            assert_eq!(last_event(), Event::bridge(RawEvent::ThresholdChanged(ETH, Direction::Deposit, ApprovalThreshold::Count(3))));
+            assert_eq!(Bridge::threshold((ETH, Direction::Withdraw)), ApprovalThreshold::Vote(VoteThreshold::SuperMajorityApprove));
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_count_threshold_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Deposit, ApprovalThreshold::Count(3)));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(3, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_with_stake_fraction_threshold_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            // Authority 1 holds half of the 4000 bonded authority stake
+            assert_ok!(Bridge::bond(Origin::signed(1), 1000));
+            assert_ok!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Withdraw, ApprovalThreshold::StakeFraction(2, 3)));
+            let cross_chain_proof = b"a sent b 1 ETH";
+            assert_ok!(withdraw(5, 10, cross_chain_proof));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_withdraw(2, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 90);
+        });
+    }
+
+    #[test]
+    fn authority_quorum_should_follow_authority_set() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_eq!(Bridge::quorum(), 2);
+            Session::set_validators(&[1, 2, 3, 4, 5]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3, 4, 5]);
+            assert_eq!(Bridge::quorum(), 4);
+            Session::set_validators(&[1, 2, 3, 4, 5, 6]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_eq!(Bridge::quorum(), 4);
+        });
+    }
+
+    #[test]
+    fn sign_deposit_with_authority_quorum_should_ignore_stake() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            Session::set_validators(&[1, 2, 3, 4]);
+            <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+            assert_ok!(Bridge::set_threshold(Origin::ROOT, ETH, Direction::Deposit, ApprovalThreshold::AuthorityQuorum));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            // Authority 4 has no bonded stake but counts towards the quorum of 3
+            assert_ok!(sign_deposit(4, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    fn rotate_authorities(validators: &[u64]) {
+        Session::set_validators(validators);
+        <Bridge as session::OnSessionChange<u64>>::on_session_change(0, false);
+    }
+
+    #[test]
+    fn session_change_should_record_authority_set_history() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_eq!(Bridge::authority_set_id(), 0);
+            System::set_block_number(5);
+            rotate_authorities(&[2, 3, 4]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+            assert_eq!(Bridge::authority_set(0), Some((vec![1, 2, 3], 5)));
+            // Reinstalling the same set doesn't start a new one
+            rotate_authorities(&[2, 3, 4]);
+            assert_eq!(Bridge::authority_set_id(), 1);
+        });
+    }
+
+    #[test]
+    fn retired_authority_sets_should_be_pruned_once_unreferenced() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_handoff_window(Origin::ROOT, ETH, 10));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_eq!(Bridge::open_records(0), 1);
+            rotate_authorities(&[2, 3, 4]);
+
+            // Kept within the handoff window
+            Bridge::on_finalise(1);
+            assert!(Bridge::authority_set(0).is_some());
+
+            // Kept past the window while a record opened under it is pending, as the
+            // window may be extended again
+            System::set_block_number(11);
+            Bridge::on_finalise(11);
+            assert!(Bridge::authority_set(0).is_some());
+
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_ok!(sign_deposit(3, 5, hash, 10));
+            assert_eq!(Bridge::open_records(0), 0);
+            Bridge::on_finalise(11);
+            assert!(System::events().iter().any(|record| record.event == Event::bridge(RawEvent::AuthoritySetPruned(0))));
+            assert_eq!(Bridge::authority_set(0), None);
+            assert_eq!(Bridge::oldest_authority_set(), 1);
+        });
+    }
+
+    #[test]
+    fn retired_signatures_should_not_count_without_handoff_window() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::bond(Origin::signed(4), 100));
+            let hash = Blake2Hasher::hash(b"a sends money to b");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_eq!(Bridge::deposit_of((ETH, hash)).unwrap().authority_set_id, 0);
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            rotate_authorities(&[2, 3, 4]);
+            assert_eq!(sign_deposit(1, 5, hash, 10), Err("Invalid non-authority sender"));
+            // The retired signer's stake no longer counts, the new set carries on
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 100);
+            assert_ok!(sign_deposit(3, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn slashed_authorities_should_not_sign_through_any_retired_set() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_handoff_window(Origin::ROOT, ETH, 10));
+            let first = Blake2Hasher::hash(b"a sends money to b");
+            let fraudulent = Blake2Hasher::hash(b"a never sent money to b");
+            assert_ok!(deposit(5, 5, first, 10));
+            assert_ok!(deposit(5, 5, fraudulent, 10));
+            assert_ok!(sign_deposit(1, 5, fraudulent, 10));
+            rotate_authorities(&[2, 3, 4]);
+            rotate_authorities(&[3, 4]);
+
+            assert_ok!(Bridge::report_fraudulent_deposit(Origin::ROOT, ETH, fraudulent));
+            assert_eq!(Bridge::authority_set(0), Some((vec![2, 3], 1)));
+            assert_eq!(sign_deposit(1, 5, first, 10), Err("Invalid non-authority sender"));
+        });
+    }
+
+    #[test]
+    fn retired_signatures_should_count_within_handoff_window() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_handoff_window(Origin::ROOT, ETH, 10));
+            let first = Blake2Hasher::hash(b"a sends money to b");
+            let second = Blake2Hasher::hash(b"c sends money to d");
+            assert_ok!(deposit(5, 5, first, 10));
+            assert_ok!(deposit(5, 5, second, 10));
+            assert_ok!(sign_deposit(1, 5, first, 10));
+            assert_ok!(sign_deposit(1, 5, second, 10));
+            rotate_authorities(&[2, 3, 4]);
+
+            // Approved by the retired set within the window
+            assert_ok!(sign_deposit(2, 5, first, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+
+            // After the window only the current set counts
+            System::set_block_number(11);
+            assert_eq!(sign_deposit(1, 5, second, 10), Err("Invalid non-authority sender"));
+            assert_ok!(sign_deposit(2, 5, second, 10));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn governance_authorities_should_join_on_session_change() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert!(Bridge::propose_authority(Origin::signed(1), 4).is_err());
+            assert_ok!(Bridge::propose_authority(Origin::ROOT, 4));
+            assert_eq!(Bridge::propose_authority(Origin::ROOT, 4), Err("Authority already approved"));
+            // Validators stay the only source until the mode changes
+            rotate_authorities(&[1, 2]);
+            assert_eq!(Bridge::authorities(), vec![1, 2]);
+
+            assert_ok!(Bridge::set_authority_source(Origin::ROOT, AuthoritySource::Union));
+            assert_ok!(Bridge::propose_authority(Origin::ROOT, 2));
+            rotate_authorities(&[1, 2, 3]);
+            assert_eq!(Bridge::authorities(), vec![1, 2, 3, 4]);
+            assert_eq!(last_event(), Event::bridge(RawEvent::NewAuthorities(vec![
+                (1, MemberSource::Validator),
+                (2, MemberSource::ValidatorAndGovernance),
+                (3, MemberSource::Validator),
+                (4, MemberSource::Governance),
+            ])));
+
+            assert_ok!(Bridge::set_authority_source(Origin::ROOT, AuthoritySource::Governance));
+            assert_ok!(Bridge::remove_authority(Origin::ROOT, 2));
+            rotate_authorities(&[1, 2, 3]);
+            assert_eq!(Bridge::authorities(), vec![4]);
+            assert_eq!(Bridge::remove_authority(Origin::ROOT, 2), Err("Invalid non-member authority"));
+        });
+    }
+
+    #[test]
+    fn candidacy_should_reserve_bond() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            assert_ok!(Bridge::submit_candidacy(Origin::signed(4)));
+            assert_eq!(Bridge::submit_candidacy(Origin::signed(4)), Err("Candidacy already submitted"));
+            assert_eq!(Balances::reserved_balance(&4), 50);
+            assert_ok!(Bridge::withdraw_candidacy(Origin::signed(4)));
+            assert_eq!(Balances::reserved_balance(&4), 0);
+            assert_eq!(Bridge::candidates(), vec![]);
+
+            // Accepted candidates get their bond back
+            assert_ok!(Bridge::submit_candidacy(Origin::signed(4)));
+            assert_ok!(Bridge::propose_authority(Origin::ROOT, 4));
+            assert_eq!(Balances::reserved_balance(&4), 0);
+            assert_eq!(Bridge::governance_authorities(), vec![4]);
+
+            // Rejected candidates forfeit it
+            assert_ok!(Bridge::submit_candidacy(Origin::signed(5)));
+            assert_ok!(Bridge::remove_authority(Origin::ROOT, 5));
+            assert_eq!(Balances::total_balance(&5), 50);
+            assert_eq!(Bridge::candidates(), vec![]);
+        });
+    }
+
+    #[test]
+    fn proof_backed_deposit_should_execute_without_signatures() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let (hash, proof) = setup_lock(5, 10);
+            assert_ok!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof)));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(Bridge::deposit_of((ETH, hash)).unwrap().status, Status::Executed);
+            assert_eq!(deposit(5, 5, hash, 10), Err("Deposit should not exist"));
+        });
+    }
+
+    #[test]
+    fn proof_backed_deposit_without_signers_should_not_pay_fee() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::set_fees(Origin::ROOT, Permill::from_percent(10), Permill::from_percent(0), 0, Permill::from_percent(0)));
+            let (hash, proof) = setup_lock(5, 10);
+            assert_ok!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof)));
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(Bridge::rewards(1), 0);
+        });
+    }
+
+    #[test]
+    fn deposit_with_invalid_proof_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let (hash, proof) = setup_lock(5, 10);
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 11, Some(proof.clone())), Err("No matching lock event in receipt"));
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 4, hash, 10, Some(proof.clone())), Err("No matching lock event in receipt"));
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, H256::from([0x55u8; 32]), 10, Some(proof.clone())), Err("Transaction hashes do not match"));
+            assert_eq!(Bridge::deposit(Origin::signed(5), EVM, 5, hash, 10, Some(proof.clone())), Err("Bridge contract not configured"));
+
+            let receipt_proof = match proof {
+                DepositProof::EthereumReceipt(proof) => proof,
+                _ => unreachable!(),
+            };
+            let mut unknown_block = receipt_proof.clone();
+            unknown_block.block_hash = H256::from([0x66u8; 32]);
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(DepositProof::EthereumReceipt(unknown_block))), Err("Unknown block for deposit proof"));
+            let mut tampered = receipt_proof.clone();
+            tampered.receipt_proof[1][0] ^= 1;
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(DepositProof::EthereumReceipt(tampered))), Err("Invalid trie proof"));
+            let mut wrong_index = receipt_proof.clone();
+            wrong_index.transaction_index = 2;
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(DepositProof::EthereumReceipt(wrong_index))), Err("Key not found in trie"));
+            assert_eq!(Balances::total_balance(&5), 100);
+
+            // Amounts are compared at full width
+            let mut amount = [0u8; 32];
+            amount[23] = 1;
+            amount[31] = 10;
+            assert_ne!(ethereum::decode_uint(&amount), Some(U256::from(10)));
+        });
+    }
+
+    #[test]
+    fn proof_backed_deposit_should_settle_pending_record() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let (hash, proof) = setup_lock(5, 10);
+            assert_ok!(deposit(4, 4, hash, 50));
+            assert_ok!(sign_deposit(1, 4, hash, 50));
+            assert_ok!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof.clone())));
+
+            let record = Bridge::deposit_of((ETH, hash)).unwrap();
+            assert_eq!((record.target, record.quantity, record.status), (5, 10, Status::Executed));
+            assert!(record.signers.is_empty());
+            assert_eq!(Balances::total_balance(&4), 100);
+            assert_eq!(Balances::total_balance(&5), 110);
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof)), Err("Deposit should not exist"));
+        });
+    }
+
+    fn attest_header(hash: H256) {
+        assert_ok!(Bridge::attest_header(Origin::signed(1), ETH, hash));
+        assert_ok!(Bridge::attest_header(Origin::signed(2), ETH, hash));
+    }
+
+    #[test]
+    fn relayed_headers_should_follow_total_difficulty() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let roots = (H256::default(), H256::default());
+            let checkpoint = eth_header(H256::default(), 1, 10, 131072, roots);
+            let checkpoint_hash = ethereum::keccak256(&checkpoint);
+            assert_ok!(Bridge::set_checkpoint_header(Origin::ROOT, ETH, checkpoint, U256::from(131072)));
+            assert_eq!(Bridge::best_header(ETH), Some(checkpoint_hash));
+
+            let a2 = eth_header(checkpoint_hash, 2, 20, 131072, roots);
+            let a2_hash = ethereum::keccak256(&a2);
+            assert_ok!(Bridge::submit_header(Origin::signed(1), ETH, a2.clone()));
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, a2), Err("Header already imported"));
+            assert_eq!(Bridge::best_header(ETH), Some(checkpoint_hash));
+            attest_header(a2_hash);
+            assert_eq!(Bridge::best_header(ETH), Some(a2_hash));
+
+            // A heavier sibling takes over as the canonical head
+            let b2 = eth_header(checkpoint_hash, 2, 21, 131200, roots);
+            let b2_hash = ethereum::keccak256(&b2);
+            assert_eq!(Bridge::submit_header(Origin::signed(5), ETH, b2.clone()), Err("Invalid unbonded relayer"));
+            assert_ok!(Bridge::bond(Origin::signed(5), 50));
+            assert_ok!(Bridge::submit_header(Origin::signed(5), ETH, b2));
+            attest_header(b2_hash);
+            assert_eq!(Bridge::best_header(ETH), Some(b2_hash));
+            assert_eq!(Bridge::canonical_header((ETH, 2)), Some(b2_hash));
+
+            // Extending the lighter branch reorganises back onto it
+            let a3 = eth_header(a2_hash, 3, 30, 131072, roots);
+            let a3_hash = ethereum::keccak256(&a3);
+            assert_ok!(Bridge::submit_header(Origin::signed(2), ETH, a3));
+            attest_header(a3_hash);
+            assert_eq!(Bridge::best_header(ETH), Some(a3_hash));
+            assert_eq!(Bridge::canonical_header((ETH, 2)), Some(a2_hash));
+            assert_eq!(last_event(), Event::bridge(RawEvent::NewBestHeader(ETH, a3_hash, 3)));
+        });
+    }
+
+    #[test]
+    fn invalid_relayed_headers_should_not_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let roots = (H256::default(), H256::default());
+            let checkpoint = eth_header(H256::default(), 1, 10, 131072, roots);
+            let checkpoint_hash = ethereum::keccak256(&checkpoint);
+            assert_ok!(Bridge::set_checkpoint_header(Origin::ROOT, ETH, checkpoint, U256::from(131072)));
+
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, eth_header(H256::from([1u8; 32]), 2, 20, 131072, roots)), Err("Unknown parent header"));
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, eth_header(checkpoint_hash, 3, 20, 131072, roots)), Err("Invalid header number"));
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, eth_header(checkpoint_hash, 2, 10, 131072, roots)), Err("Invalid header timestamp"));
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, eth_header(checkpoint_hash, 2, 20, 140000, roots)), Err("Invalid header difficulty"));
+            assert_eq!(Bridge::submit_header(Origin::signed(1), ETH, vec![0xc0]), Err("Invalid header"));
+        });
+    }
+
+    #[test]
+    fn attested_headers_should_be_challengeable() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let roots = (H256::default(), H256::default());
+            let checkpoint = eth_header(H256::default(), 1, 10, 131072, roots);
+            let checkpoint_hash = ethereum::keccak256(&checkpoint);
+            assert_ok!(Bridge::set_checkpoint_header(Origin::ROOT, ETH, checkpoint, U256::from(131072)));
+            assert_ok!(Bridge::set_header_challenge_period(Origin::ROOT, ETH, 5));
+            assert_ok!(Bridge::set_confirmation_depth(Origin::ROOT, ETH, 1));
+
+            let a2 = eth_header(checkpoint_hash, 2, 20, 131072, roots);
+            let a2_hash = ethereum::keccak256(&a2);
+            let a3 = eth_header(a2_hash, 3, 30, 131072, roots);
+            let a3_hash = ethereum::keccak256(&a3);
+            assert_ok!(Bridge::submit_header(Origin::signed(1), ETH, a2));
+            assert_ok!(Bridge::submit_header(Origin::signed(1), ETH, a3));
+            assert_eq!(Bridge::attest_header(Origin::signed(1), ETH, a3_hash), Err("Parent header not attested"));
+            assert_eq!(Bridge::attest_header(Origin::signed(4), ETH, a2_hash), Err("Invalid non-authority sender"));
+            assert_ok!(Bridge::attest_header(Origin::signed(1), ETH, a2_hash));
+            assert_eq!(Bridge::attest_header(Origin::signed(1), ETH, a2_hash), Err("Invalid duplicate attestation"));
+            assert_eq!(Bridge::best_header(ETH), Some(checkpoint_hash));
+            assert_ok!(Bridge::attest_header(Origin::signed(2), ETH, a2_hash));
+            attest_header(a3_hash);
+            assert_eq!(Bridge::best_header(ETH), Some(a3_hash));
+            assert_eq!(Bridge::header_final_at((ETH, a2_hash)), Some(6));
+
+            // Attested headers only back proofs once they can no longer be challenged
+            assert_eq!(Bridge::confirmed_roots(ETH, a2_hash), None);
+            assert!(Bridge::challenge_header(Origin::signed(5), ETH, a2_hash).is_err());
+            // Guardians can only pause, not slash
+            assert!(Bridge::challenge_header(Origin::signed(6), ETH, a2_hash).is_err());
+            assert_ok!(Bridge::challenge_header(Origin::ROOT, ETH, a2_hash));
+            assert_eq!(Bridge::header((ETH, a2_hash)), None);
+            assert_eq!(Bridge::header((ETH, a3_hash)), None);
+            assert_eq!(Bridge::best_header(ETH), Some(checkpoint_hash));
+            assert_eq!(Bridge::canonical_header((ETH, 2)), None);
+            assert_eq!(Bridge::authorities(), vec![3]);
+            assert_eq!(Bridge::bonded(1), 0);
+            assert_eq!(Bridge::challenge_header(Origin::ROOT, ETH, checkpoint_hash), Err("Header not challengeable"));
+        });
+    }
+
+    #[test]
+    fn relayed_header_roots_should_back_deposits_once_confirmed() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let (transactions_root, receipts_root, mut proof) = lock_block(5, 10);
+            let checkpoint = eth_header(H256::default(), 1, 10, 131072, (transactions_root, receipts_root));
+            let checkpoint_hash = ethereum::keccak256(&checkpoint);
+            assert_ok!(Bridge::set_checkpoint_header(Origin::ROOT, ETH, checkpoint, U256::from(131072)));
+            assert_ok!(Bridge::set_confirmation_depth(Origin::ROOT, ETH, 1));
+            proof.block_hash = checkpoint_hash;
+            let hash = ethereum::keccak256(b"lock transaction");
+            let proof = DepositProof::EthereumReceipt(proof);
+
+            assert_eq!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof.clone())), Err("Unknown block for deposit proof"));
+            let child = eth_header(checkpoint_hash, 2, 20, 131072, (H256::default(), H256::default()));
+            assert_ok!(Bridge::submit_header(Origin::signed(1), ETH, child.clone()));
+            assert_eq!(Bridge::confirmed_roots(ETH, checkpoint_hash), None);
+            attest_header(ethereum::keccak256(&child));
+            assert_eq!(Bridge::confirmed_roots(ETH, checkpoint_hash), Some((transactions_root, receipts_root)));
+            assert_ok!(Bridge::deposit(Origin::signed(5), ETH, 5, hash, 10, Some(proof)));
+            assert_eq!(Balances::total_balance(&5), 110);
+        });
+    }
+
+    #[test]
+    fn bitcoin_deposit_should_verify_spv_proof() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let checkpoint = btc_header(H256::default(), H256::default(), 1000, REGTEST_BITS, true);
+            setup_bitcoin(&checkpoint, 100, 1000);
+            assert_eq!(Bridge::set_confirmation_depth(Origin::ROOT, BTC, 0), Err("Invalid zero confirmation depth"));
+            assert_ok!(Bridge::set_confirmation_depth(Origin::ROOT, BTC, 1));
+
+            let transaction = btc_transaction(&[(10, federation_script()), (0, op_return(&5u64.encode()))]);
+            let txid = bitcoin::sha256d(&transaction);
+            let sibling = H256::from([0x88u8; 32]);
+            let merkle_root = bitcoin::merkle_root(txid, &[sibling], 0);
+            let block = btc_header(bitcoin::sha256d(&checkpoint), merkle_root, 1600, REGTEST_BITS, true);
+            let block_hash = bitcoin::sha256d(&block);
+            assert_ok!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, block.clone()));
+
+            let proof = |index| DepositProof::BitcoinTransaction(bitcoin::TransactionProof {
+                block_hash,
+                transaction: transaction.clone(),
+                merkle_branch: vec![sibling],
+                index,
+            });
+            assert_eq!(Bridge::deposit(Origin::signed(5), BTC, 5, txid, 10, Some(proof(0))), Err("Unknown block for deposit proof"));
+            assert_ok!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, btc_header(block_hash, H256::default(), 2200, REGTEST_BITS, true)));
+
+            assert_eq!(Bridge::deposit(Origin::signed(5), BTC, 5, txid, 10, Some(proof(1))), Err("Invalid merkle proof"));
+            assert_eq!(Bridge::deposit(Origin::signed(5), BTC, 5, txid, 11, Some(proof(0))), Err("Quantities don't match"));
+            assert_eq!(Bridge::deposit(Origin::signed(5), BTC, 4, txid, 10, Some(proof(0))), Err("No deposit target in transaction"));
+            assert_ok!(Bridge::deposit(Origin::signed(5), BTC, 5, txid, 10, Some(proof(0))));
+            assert_eq!(Balances::total_balance(&5), 110);
+
+            // Authority signatures cannot release Bitcoin, so nothing can be withdrawn to it
+            assert_eq!(Bridge::withdraw(Origin::signed(5), BTC, ForeignAddress::Bitcoin(federation_script()), 10, vec![]), Err("Withdraws to Bitcoin chains are not supported"));
+            assert_eq!(Balances::reserved_balance(&5), 0);
+        });
+    }
+
+    #[test]
+    fn bitcoin_headers_should_follow_difficulty_rules() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            // The next header starts a retarget interval that took an eighth of the
+            // expected time, so its target drops to a quarter
+            let time = 10_000_000;
+            let checkpoint = btc_header(H256::default(), H256::default(), time, REGTEST_BITS, true);
+            let checkpoint_hash = bitcoin::sha256d(&checkpoint);
+            setup_bitcoin(&checkpoint, 2015, time - bitcoin::TARGET_TIMESPAN / 8);
+
+            assert_eq!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, btc_header(H256::from([1u8; 32]), H256::default(), time + 600, REGTEST_BITS, true)), Err("Unknown parent header"));
+            assert_eq!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, btc_header(checkpoint_hash, H256::default(), time + 600, REGTEST_BITS, true)), Err("Invalid header difficulty"));
+            assert_eq!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, btc_header(checkpoint_hash, H256::default(), time + 600, 0x201fffff, false)), Err("Invalid proof of work"));
+            assert_eq!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, vec![0u8; 79]), Err("Invalid header length"));
+
+            let header = btc_header(checkpoint_hash, H256::default(), time + 600, 0x201fffff, true);
+            assert_ok!(Bridge::submit_bitcoin_header(Origin::signed(1), BTC, header.clone()));
+            assert_eq!(Bridge::best_header(BTC), Some(bitcoin::sha256d(&header)));
+            assert_eq!(Bridge::bitcoin_header((BTC, bitcoin::sha256d(&header))).unwrap().1, 2016);
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_should_verify_signature() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            let message = Bridge::withdraw_message(hash).unwrap();
+
+            // Signed by another authority's key
+            assert_eq!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Raw, sign_message(2, message)), Err("Invalid withdraw signature"));
+            // Signed over a different message
+            assert_eq!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Raw, sign_message(1, H256::from([1u8; 32]))), Err("Invalid withdraw signature"));
+            assert_eq!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Raw, b"a sent b 1 ETH".to_vec()), Err("Invalid signature length"));
+            assert_ok!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Raw, sign_message(1, message)));
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().signatures, vec![(1, SignatureScheme::Raw, sign_message(1, message))]);
+
+            // Authorities without a registered key cannot sign
+            rotate_authorities(&[1, 2, 3, 4]);
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(1).unwrap();
+            assert_eq!(sign_withdraw(4, 5, hash, 10), Err("No signing key registered"));
+            assert_ok!(register_signing_key(4));
+            assert_ok!(sign_withdraw(4, 5, hash, 10));
+        });
+    }
+
+    #[test]
+    fn register_signing_key_should_require_proof_of_possession() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            // Signed over another account or by another key
+            let proof = sign_message(4, Bridge::signing_key_proof_message(&5));
+            assert_eq!(Bridge::register_signing_key(Origin::signed(4), signing_address(4), proof), Err("Invalid signing key proof"));
+            let proof = sign_message(5, Bridge::signing_key_proof_message(&4));
+            assert_eq!(Bridge::register_signing_key(Origin::signed(4), signing_address(4), proof), Err("Invalid signing key proof"));
+
+            // Keys cannot be registered to a second account
+            let proof = sign_message(1, Bridge::signing_key_proof_message(&4));
+            assert_eq!(Bridge::register_signing_key(Origin::signed(4), signing_address(1), proof), Err("Signing key already registered"));
+            assert_ok!(register_signing_key(4));
+            assert_eq!(Bridge::signing_key_owner(signing_address(4)), Some(4));
+
+            // Rotating a key releases the previous one
+            let proof = sign_message(5, Bridge::signing_key_proof_message(&4));
+            assert_ok!(Bridge::register_signing_key(Origin::signed(4), signing_address(5), proof));
+            assert_eq!(Bridge::signing_key(4), Some(signing_address(5)));
+            assert_eq!(Bridge::signing_key_owner(signing_address(4)), None);
+        });
+    }
+
+    #[test]
+    fn authority_withdraw_should_count_only_valid_signatures() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::withdraw(Origin::signed(1), ETH, recipient(), 10, b"a sent b 1 ETH".to_vec()));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().signatures, vec![]);
+
+            let message = ethereum::withdraw_message(ETH_EVM_CHAIN_ID, &bridge_contract(), &H160::from([0x11u8; 20]), U256::from(10), 1, 0);
+            assert_ok!(Bridge::withdraw(Origin::signed(1), ETH, recipient(), 10, sign_message(1, message)));
+            let hash = Bridge::withdraw_record_hash(1).unwrap();
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().signatures, vec![(1, SignatureScheme::Raw, sign_message(1, message))]);
+        });
+    }
+
+    #[test]
+    fn withdraw_message_should_be_abi_packed() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let word = |value: u8| {
+                let mut word = vec![0u8; 32];
+                word[31] = value;
+                word
+            };
+            let mut packed = word(ETH_EVM_CHAIN_ID as u8);
+            packed.extend_from_slice(&[0x33u8; 20]);
+            packed.extend_from_slice(&[0x11u8; 20]);
+            packed.extend(word(10));
+            packed.extend(word(0));
+            packed.extend(word(0));
+
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_eq!(Bridge::withdraw_message(hash), Some(ethereum::keccak256(&packed)));
+
+            // The nonce and authority set id of the record are covered
+            rotate_authorities(&[1, 2]);
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(1).unwrap();
+            assert_eq!(Bridge::withdraw_message(hash), Some(ethereum::withdraw_message(ETH_EVM_CHAIN_ID, &bridge_contract(), &H160::from([0x11u8; 20]), U256::from(10), 1, 1)));
+
+            assert_eq!(Bridge::withdraw(Origin::signed(5), EVM, recipient(), 10, vec![]), Err("Bridge contract not configured"));
+            assert_ok!(Bridge::set_bridge_contract(Origin::ROOT, EVM, bridge_contract()));
+            assert_eq!(Bridge::withdraw(Origin::signed(5), EVM, recipient(), 10, vec![]), Err("EVM chain id not configured"));
+            assert_eq!(Bridge::domain_separator(EVM), None);
+        });
+    }
+
+    #[test]
+    fn set_evm_chain_id_should_work() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(Bridge::register_chain(Origin::ROOT, BTC, b"Bitcoin".to_vec(), AddressFormat::Bitcoin));
+            assert!(Bridge::set_evm_chain_id(Origin::signed(1), EVM, 7).is_err());
+            assert_eq!(Bridge::set_evm_chain_id(Origin::ROOT, BTC, 7), Err("Chain is not an EVM chain"));
+            assert_eq!(Bridge::set_evm_chain_id(Origin::ROOT, 9, 7), Err("Chain is not registered"));
+            assert_ok!(Bridge::set_evm_chain_id(Origin::ROOT, EVM, 7));
+            assert_eq!(Bridge::chains(EVM).unwrap().evm_chain_id, Some(7));
+            assert_eq!(Bridge::chains(ETH).unwrap().evm_chain_id, Some(ETH_EVM_CHAIN_ID));
+        });
+    }
+
+    #[test]
+    fn sign_withdraw_should_accept_eip712_signatures() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let hash = Bridge::withdraw_record_hash(0).unwrap();
+
+            let word = |value: u8| {
+                let mut word = vec![0u8; 32];
+                word[31] = value;
+                word
+            };
+            let mut domain = ethereum::keccak256(b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)").to_vec();
+            domain.extend_from_slice(&ethereum::keccak256(b"Edgeware Bridge")[..]);
+            domain.extend_from_slice(&ethereum::keccak256(b"1")[..]);
+            domain.extend(word(ETH_EVM_CHAIN_ID as u8));
+            domain.extend_from_slice(&[0u8; 12]);
+            domain.extend_from_slice(&[0x33u8; 20]);
+            assert_eq!(Bridge::domain_separator(ETH), Some(ethereum::keccak256(&domain)));
+
+            let mut withdraw = ethereum::keccak256(b"Withdraw(address recipient,uint256 amount,uint256 nonce,uint256 authoritySetId)").to_vec();
+            withdraw.extend_from_slice(&[0u8; 12]);
+            withdraw.extend_from_slice(&[0x11u8; 20]);
+            withdraw.extend(word(10));
+            withdraw.extend(word(0));
+            withdraw.extend(word(0));
+            assert_eq!(Bridge::withdraw_struct_hash(hash), Some(ethereum::keccak256(&withdraw)));
+
+            let mut typed = vec![0x19, 0x01];
+            typed.extend_from_slice(&ethereum::keccak256(&domain)[..]);
+            typed.extend_from_slice(&ethereum::keccak256(&withdraw)[..]);
+            let digest = ethereum::keccak256(&typed);
+            assert_eq!(Bridge::withdraw_typed_data_hash(hash), Some(digest));
+
+            // Both schemes stay bound to the bridge contract the withdraw was requested for
+            assert_ok!(Bridge::set_bridge_contract(Origin::ROOT, ETH, H160::from([0x44u8; 20])));
+            assert_eq!(Bridge::withdraw_typed_data_hash(hash), Some(digest));
+
+            // Signatures must match the scheme they are submitted under
+            let message = Bridge::withdraw_message(hash).unwrap();
+            assert_eq!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Eip712, sign_message(1, message)), Err("Invalid withdraw signature"));
+            assert_ok!(Bridge::sign_withdraw(Origin::signed(1), 5, hash, 10, recipient(), SignatureScheme::Eip712, sign_message(1, digest)));
+            assert_ok!(sign_withdraw(2, 5, hash, 10));
+            assert_eq!(Balances::total_balance(&5), 90);
+            assert_eq!(Bridge::withdraw_of(hash).unwrap().signatures, vec![
+                (1, SignatureScheme::Eip712, sign_message(1, digest)),
+                (2, SignatureScheme::Raw, sign_message(2, message)),
+            ]);
+        });
+    }
+
+    #[test]
+    fn status_transitions_should_be_checked() {
+        assert_eq!(Status::Pending.transition(Status::Approved), Ok(Status::Approved));
+        assert_eq!(Status::Pending.transition(Status::Cancelled), Ok(Status::Cancelled));
+        assert_eq!(Status::Approved.transition(Status::Failed), Ok(Status::Failed));
+        assert_eq!(Status::Approved.transition(Status::Rejected), Ok(Status::Rejected));
+        assert_eq!(Status::Pending.transition(Status::Executed), Err("Invalid status transition"));
+        assert_eq!(Status::Approved.transition(Status::Cancelled), Err("Invalid status transition"));
+        assert_eq!(Status::Executed.transition(Status::Rejected), Err("Invalid status transition"));
+        assert_eq!(Status::Expired.transition(Status::Approved), Err("Invalid status transition"));
+    }
+
+    #[test]
+    fn fresh_chain_should_start_at_current_storage_version() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert_eq!(Bridge::layout_version(), bridge::STORAGE_VERSION);
+            assert_ok!(sign_deposit(1, 5, Blake2Hasher::hash(b"a sent b 1 ETH"), 10));
+            Bridge::on_finalise(1);
+            assert_eq!(Bridge::migration_cursor(), 0);
+            assert_eq!(Bridge::pruning_held_from(), None);
+        });
+    }
+
+    #[test]
+    fn legacy_records_should_be_migrated_in_chunks() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let first = Blake2Hasher::hash(b"a sent b 1 ETH");
+            let second = Blake2Hasher::hash(b"a sent b 2 ETH");
+            let withdraw_hash = Blake2Hasher::hash(b"b sent a 1 ETH");
+            let legacy_withdraw = (0u32, 5u64, 10u64, vec![(1u64, vec![1u8])], true);
+
+            // Seed records as tuples, with deposits keyed by transaction hash alone as
+            // they were before chains were registered, and no storage version as the
+            // baseline layout did not store one
+            <bridge::StorageVersion<Test>>::kill();
+            let mut first_key = b"BridgeStorage DepositOf".to_vec();
+            first_key.extend(first.encode());
+            runtime_support::storage::put(&first_key[..], &(0u32, 5u64, 10u64, vec![1u64], true));
+            let mut second_key = b"BridgeStorage DepositOf".to_vec();
+            second_key.extend(second.encode());
+            runtime_support::storage::put(&second_key[..], &(1u32, 5u64, 20u64, Vec::<u64>::new(), false));
+            let withdraw_key = <bridge::WithdrawOf<Test>>::key_for(&withdraw_hash);
+            runtime_support::storage::put(&withdraw_key[..], &legacy_withdraw);
+            runtime_support::storage::put(b"BridgeStorage Deposits", &vec![first, second]);
+            runtime_support::storage::put(b"BridgeStorage Withdraws", &vec![withdraw_hash]);
+            <bridge::DepositCount<Test>>::put(2);
+            <bridge::WithdrawCount<Test>>::put(1);
+            <bridge::PruneQueue<Test>>::insert(2, vec![(Direction::Deposit, ETH, first)]);
+            assert_ok!(Bridge::set_record_retention(Origin::ROOT, 10));
+
+            // The first block only migrates the deposits and records stay locked
+            Bridge::on_finalise(1);
+            assert_eq!(Bridge::layout_version(), 0);
+            assert_eq!(Bridge::migration_cursor(), 2);
+            assert_eq!(Bridge::deposit_of((ETH, first)).unwrap().status, Status::Executed);
+            assert!(!runtime_support::storage::exists(&first_key[..]));
+            assert_eq!(runtime_support::storage::get(&withdraw_key[..]), Some(legacy_withdraw.clone()));
+            assert_eq!(sign_deposit(1, 5, second, 20), Err("Storage migration in progress"));
+            assert_eq!(withdraw(5, 10, b"a sent b 1 ETH"), Err("Storage migration in progress"));
+            assert_eq!(Bridge::pruning_held_from(), Some(1));
+
+            System::set_block_number(2);
+            Bridge::on_finalise(2);
+            assert_eq!(last_event(), Event::bridge(RawEvent::MigrationCompleted(1)));
+            assert_eq!(Bridge::storage_version(), 1);
+            assert_eq!(Bridge::migration_cursor(), 0);
+
+            // Records are then indexed by the index they were created with
+            System::set_block_number(3);
+            Bridge::on_finalise(3);
+            assert_eq!(Bridge::deposit_by_index(1), Some((ETH, second)));
+            assert_eq!(Bridge::withdraw_record_hash(0), None);
+            System::set_block_number(4);
+            Bridge::on_finalise(4);
+            assert_eq!(last_event(), Event::bridge(RawEvent::MigrationCompleted(2)));
+            assert_eq!(Bridge::deposit_by_index(0), Some((ETH, first)));
+            assert_eq!(Bridge::withdraw_record_hash(0), Some(withdraw_hash));
+            assert!(!runtime_support::storage::exists(b"BridgeStorage Deposits"));
+
+            // Final records are finally scheduled for pruning
+            System::set_block_number(5);
+            Bridge::on_finalise(5);
+            assert_eq!(Bridge::prune_queue(15), vec![(Direction::Deposit, ETH, first)]);
+            System::set_block_number(6);
+            Bridge::on_finalise(6);
+            assert_eq!(last_event(), Event::bridge(RawEvent::MigrationCompleted(3)));
+            assert_eq!(Bridge::storage_version(), bridge::STORAGE_VERSION);
+            assert_eq!(Bridge::prune_queue(16), vec![(Direction::Withdraw, ETH, withdraw_hash)]);
+            assert_eq!(Bridge::deposit_of((ETH, first)), Some(DepositRecord {
+                index: 0,
+                chain_id: ETH,
+                target: 5,
+                quantity: 10,
+                signers: vec![1],
+                status: Status::Executed,
+                created_at: 1,
+                authority_set_id: 0,
+            }));
+            assert_eq!(Bridge::deposit_of((ETH, second)).unwrap().status, Status::Pending);
+            // Completed deposits cannot be minted again on the legacy chain
+            assert_eq!(deposit(5, 5, first, 10), Err("Deposit should not exist"));
+
+            assert_eq!(Bridge::withdraw_of(withdraw_hash), Some(WithdrawRecord {
+                index: 0,
+                chain_id: ETH,
+                sender: 5,
+                quantity: 10,
+                fee: 0,
+                recipient: ForeignAddress::Evm(H160::zero()),
+                signatures: vec![(1, SignatureScheme::Raw, vec![1])],
+                status: Status::Executed,
+                created_at: 2,
+                authority_set_id: 0,
+                message: H256::zero(),
+                typed_data_hash: H256::zero(),
+            }));
+            assert_ok!(sign_deposit(1, 5, second, 20));
+
+            // Records due while storage was being migrated are pruned afterwards
+            assert_eq!(Bridge::prune_queue(2), vec![(Direction::Deposit, ETH, first)]);
+            System::set_block_number(7);
+            Bridge::on_finalise(7);
+            assert_eq!(Bridge::pruning_held_from(), Some(3));
+            assert_eq!(Bridge::prune_queue(2), vec![]);
+            assert_eq!(Bridge::deposit_of((ETH, first)), None);
+            assert_eq!(Bridge::deposit_by_index(0), None);
+            assert!(Bridge::deposit_commitment((ETH, first)).is_some());
+        });
+    }
+
+    #[test]
+    fn final_records_should_be_pruned_after_retention() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            assert!(Bridge::set_record_retention(Origin::signed(1), 5).is_err());
+            assert_ok!(Bridge::set_record_retention(Origin::ROOT, 5));
+            let hash = Blake2Hasher::hash(b"a sent b 1 ETH");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            let deposit_record = Bridge::deposit_of((ETH, hash)).unwrap();
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let record_hash = Bridge::withdraw_record_hash(0).unwrap();
+            // Pending records are never pruned
+            assert_ok!(withdraw(5, 20, b"a sent b 2 ETH"));
+            let pending_hash = Bridge::withdraw_record_hash(1).unwrap();
+            Bridge::on_finalise(1);
+            System::set_block_number(2);
+            assert_ok!(Bridge::cancel_withdraw(Origin::signed(5), record_hash));
+            let withdraw_record = Bridge::withdraw_of(record_hash).unwrap();
+
+            System::set_block_number(6);
+            Bridge::on_finalise(6);
+            let deposit_commitment = BlakeTwo256::hash_of(&deposit_record);
+            assert_eq!(last_event(), Event::bridge(RawEvent::DepositPruned(ETH, hash, deposit_commitment)));
+            assert_eq!(Bridge::deposit_of((ETH, hash)), None);
+            assert_eq!(Bridge::deposit_commitment((ETH, hash)), Some((deposit_commitment, vec![1, 2])));
+            assert_eq!(Bridge::deposit_by_index(0), None);
+            assert!(Bridge::withdraw_of(record_hash).is_some());
+
+            System::set_block_number(7);
+            Bridge::on_finalise(7);
+            let withdraw_commitment = BlakeTwo256::hash_of(&withdraw_record);
+            assert_eq!(last_event(), Event::bridge(RawEvent::WithdrawPruned(ETH, record_hash, withdraw_commitment)));
+            assert_eq!(Bridge::withdraw_of(record_hash), None);
+            assert_eq!(Bridge::withdraw_commitment(record_hash), Some(withdraw_commitment));
+            assert_eq!(Bridge::withdraw_record_hash(0), None);
+            assert!(Bridge::withdraw_of(pending_hash).is_some());
+
+            // Pruned deposits still cannot be deposited twice
+            assert_eq!(deposit(5, 5, hash, 10), Err("Deposit should not exist"));
+            assert_eq!(Balances::total_balance(&5), 110);
+
+            // Signers of pruned deposits can still be slashed
+            assert_ok!(Bridge::report_fraudulent_deposit(Origin::ROOT, ETH, hash));
+            assert_eq!(Bridge::bonded(1), 0);
+            assert_eq!(Bridge::bonded(2), 0);
+            assert_eq!(Bridge::bonded(3), 1000);
+        });
+    }
+
+    #[test]
+    fn final_records_should_be_retained_until_retention_is_set() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let first = Blake2Hasher::hash(b"a sent b 1 ETH");
+            let second = Blake2Hasher::hash(b"a sent b 2 ETH");
+            let third = Blake2Hasher::hash(b"a sent b 3 ETH");
+            for hash in [first, second, third].iter() {
+                assert_ok!(deposit(5, 5, *hash, 10));
+                assert_ok!(sign_deposit(1, 5, *hash, 10));
+                assert_ok!(sign_deposit(2, 5, *hash, 10));
+            }
+            assert_eq!(Bridge::retained_count(), 3);
+            assert_eq!(Bridge::retained_record(0), Some((Direction::Deposit, ETH, first)));
+
+            System::set_block_number(3);
+            Bridge::on_finalise(3);
+            assert!(Bridge::deposit_of((ETH, first)).is_some());
+            assert_ok!(Bridge::set_record_retention(Origin::ROOT, 5));
+
+            // Retained records are scheduled a batch per block
+            System::set_block_number(4);
+            Bridge::on_finalise(4);
+            assert_eq!(Bridge::prune_queue(9), vec![(Direction::Deposit, ETH, first), (Direction::Deposit, ETH, second)]);
+            assert_eq!(Bridge::retained_record(0), None);
+            assert_eq!(Bridge::retained_cursor(), 2);
+            System::set_block_number(5);
+            Bridge::on_finalise(5);
+            assert_eq!(Bridge::prune_queue(10), vec![(Direction::Deposit, ETH, third)]);
+            assert_eq!(Bridge::retained_cursor(), 3);
+
+            System::set_block_number(9);
+            Bridge::on_finalise(9);
+            assert_eq!(Bridge::deposit_of((ETH, first)), None);
+            assert!(Bridge::deposit_commitment((ETH, first)).is_some());
+            assert!(Bridge::deposit_of((ETH, third)).is_some());
+        });
+    }
+
+    #[test]
+    fn mmr_proofs_should_verify() {
+        let leaves = (0..7u8).map(|i| H256::from([i; 32])).collect::<Vec<_>>();
+        let mut peaks = vec![];
+        for (count, leaf) in leaves.iter().enumerate() {
+            mmr::append(&mut peaks, count as u64, *leaf);
+        }
+        // Seven leaves make mountains of four, two and one leaves
+        assert_eq!(peaks.len(), 3);
+        assert_eq!(peaks, mmr::peaks(&leaves));
+        let first = mmr::hash_pair(&mmr::hash_pair(&leaves[0], &leaves[1]), &mmr::hash_pair(&leaves[2], &leaves[3]));
+        let second = mmr::hash_pair(&leaves[4], &leaves[5]);
+        let mut committed = ethereum::abi_uint(U256::from(7)).to_vec();
+        committed.extend_from_slice(&mmr::hash_pair(&first, &mmr::hash_pair(&second, &leaves[6]))[..]);
+        let root = ethereum::keccak256(&committed);
+        assert_eq!(mmr::root(7, &peaks), root);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = mmr::generate_proof(&leaves, index as u64).unwrap();
+            assert!(mmr::verify_proof(&root, *leaf, &proof));
+            assert!(!mmr::verify_proof(&root, H256::from([9u8; 32]), &proof));
+        }
+        let mut wrong_index = mmr::generate_proof(&leaves, 2).unwrap();
+        wrong_index.leaf_index = 3;
+        assert!(!mmr::verify_proof(&root, leaves[2], &wrong_index));
+        // Eleven leaves also make three mountains, of eight, two and one leaves
+        let mut wrong_count = mmr::generate_proof(&leaves, 6).unwrap();
+        wrong_count.leaf_count = 11;
+        assert!(!mmr::verify_proof(&root, leaves[6], &wrong_count));
+        assert_eq!(mmr::generate_proof(&leaves, 7), None);
+        assert_eq!(mmr::root(0, &[]), H256::zero());
+    }
+
+    #[test]
+    fn executed_transfers_should_be_accumulated() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            let hash = Blake2Hasher::hash(b"a sent b 1 ETH");
+            assert_ok!(deposit(5, 5, hash, 10));
+            assert_ok!(sign_deposit(1, 5, hash, 10));
+            assert_eq!(Bridge::mmr_leaf_count(), 0);
+            assert_ok!(sign_deposit(2, 5, hash, 10));
+            assert_ok!(withdraw(5, 10, b"a sent b 1 ETH"));
+            let record_hash = Bridge::withdraw_record_hash(0).unwrap();
+            assert_ok!(sign_withdraw(1, 5, record_hash, 10));
+            assert_ok!(sign_withdraw(2, 5, record_hash, 10));
+            assert_eq!(Bridge::mmr_leaf_count(), 2);
+            assert_eq!(Bridge::mmr_root(), H256::zero());
+
+            // Leaves commit to the records as they were executed
+            let deposit_record = Bridge::deposit_of((ETH, hash)).unwrap();
+            assert_eq!(deposit_record.status, Status::Executed);
+            let mut leaf = (Direction::Deposit, ETH, hash).encode();
+            leaf.extend(deposit_record.encode());
+            let deposit_leaf = ethereum::keccak256(&leaf);
+            assert_eq!(Bridge::mmr_leaf(0), Some(deposit_leaf));
+
+            Bridge::on_finalise(1);
+            let mut committed = ethereum::abi_uint(U256::from(2)).to_vec();
+            committed.extend_from_slice(&mmr::hash_pair(&deposit_leaf, &Bridge::mmr_leaf(1).unwrap())[..]);
+            let root = ethereum::keccak256(&committed);
+            assert_eq!(last_event(), Event::bridge(RawEvent::MmrRootUpdated(root, 2)));
+            assert_eq!(Bridge::mmr_root(), root);
+            let proof = Bridge::transfer_proof(0).unwrap();
+            assert!(mmr::verify_proof(&Bridge::mmr_root(), deposit_leaf, &proof));
+            assert_eq!(Bridge::transfer_proof(2), None);
+        });
+    }
+
+    #[test]
+    fn queued_transfers_should_be_accumulated_once_executed() {
+        with_externalities(&mut new_test_ext(), || {
+            System::set_block_number(1);
+            setup();
+            set_rate_limit(Direction::Deposit);
+            let first = Blake2Hasher::hash(b"a sends money to b");
+            let second = Blake2Hasher::hash(b"c sends money to d");
+            assert_ok!(deposit(5, 5, first, 80));
+            assert_ok!(sign_deposit(1, 5, first, 80));
+            assert_ok!(sign_deposit(2, 5, first, 80));
+            assert_ok!(deposit(5, 5, second, 50));
+            assert_ok!(sign_deposit(1, 5, second, 50));
+            assert_ok!(sign_deposit(2, 5, second, 50));
+            assert_eq!(Bridge::queued((ETH, Direction::Deposit)), vec![second]);
+            assert_eq!(Bridge::mmr_leaf_count(), 1);
+
+            System::set_block_number(11);
+            Bridge::on_finalise(11);
+            assert_eq!(Bridge::deposit_of((ETH, second)).unwrap().status, Status::Executed);
+            assert_eq!(Bridge::mmr_leaf_count(), 2);
+            let mut leaf = (Direction::Deposit, ETH, second).encode();
+            leaf.extend(Bridge::deposit_of((ETH, second)).unwrap().encode());
+            assert_eq!(Bridge::mmr_leaf(1), Some(ethereum::keccak256(&leaf)));
         });
     }
 }
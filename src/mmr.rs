@@ -0,0 +1,148 @@
+// Copyright 2018 Commonwealth Labs, Inc.
+// This file is part of Edgeware.
+
+// Edgeware is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Edgeware is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Edgeware.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Merkle Mountain Range accumulator of bridge transfers, hashed with keccak256
+//! so foreign chain contracts can verify its proofs cheaply.
+//!
+//! The accumulator is a list of perfect binary trees ("mountains") of strictly
+//! decreasing height, one for each set bit of the number of leaves. The runtime
+//! only keeps the root of each mountain, its peak, which is enough to append
+//! leaves and compute the root. The root commits to the number of leaves, which
+//! fixes the height of each mountain a proof is checked against. Proofs are
+//! generated from the full list of leaves outside of the runtime.
+
+use rstd::prelude::*;
+use primitives::{H256, U256};
+use ethereum;
+
+/// Proof that a leaf is part of an accumulator.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Proof {
+    /// Position of the leaf among all leaves
+    pub leaf_index: u64,
+    /// Number of leaves of the accumulator the proof is against
+    pub leaf_count: u64,
+    /// Sibling hashes from the leaf up to the peak of its mountain
+    pub siblings: Vec<H256>,
+    /// Peaks of all mountains, from the highest to the lowest
+    pub peaks: Vec<H256>,
+}
+
+/// Hash of two child nodes
+pub fn hash_pair(left: &H256, right: &H256) -> H256 {
+    let mut pair = Vec::with_capacity(64);
+    pair.extend_from_slice(&left[..]);
+    pair.extend_from_slice(&right[..]);
+    ethereum::keccak256(&pair)
+}
+
+/// Append a leaf to the peaks of an accumulator of `leaf_count` leaves, merging
+/// mountains of equal height
+pub fn append(peaks: &mut Vec<H256>, leaf_count: u64, leaf: H256) {
+    let mut node = leaf;
+    let mut count = leaf_count;
+    while count & 1 == 1 {
+        match peaks.pop() {
+            Some(left) => node = hash_pair(&left, &node),
+            None => break,
+        }
+        count >>= 1;
+    }
+    peaks.push(node);
+}
+
+/// Root of an accumulator of `leaf_count` leaves, hashing the leaf count with its
+/// peaks bagged from the lowest to the highest
+pub fn root(leaf_count: u64, peaks: &[H256]) -> H256 {
+    let mut bagged = peaks.iter().rev();
+    match bagged.next() {
+        Some(lowest) => {
+            let bagged = bagged.fold(*lowest, |acc, peak| hash_pair(peak, &acc));
+            let mut committed = Vec::with_capacity(64);
+            committed.extend_from_slice(&ethereum::abi_uint(U256::from(leaf_count)));
+            committed.extend_from_slice(&bagged[..]);
+            ethereum::keccak256(&committed)
+        },
+        None => H256::zero(),
+    }
+}
+
+/// Position of the mountain including a leaf among the peaks, along with the
+/// index of its first leaf and its height
+fn mountain_of(leaf_count: u64, leaf_index: u64) -> Option<(usize, u64, u32)> {
+    let mut offset = 0u64;
+    let mut position = 0;
+    for height in (0..64).rev() {
+        let size = 1u64 << height;
+        if leaf_count & size == 0 {
+            continue;
+        }
+        if leaf_index < offset + size {
+            return Some((position, offset, height));
+        }
+        offset += size;
+        position += 1;
+    }
+    None
+}
+
+/// Verify a proof of a leaf against the root of an accumulator
+pub fn verify_proof(root_hash: &H256, leaf: H256, proof: &Proof) -> bool {
+    let (position, offset, height) = match mountain_of(proof.leaf_count, proof.leaf_index) {
+        Some(mountain) => mountain,
+        None => return false,
+    };
+    if proof.siblings.len() != height as usize || proof.peaks.len() != proof.leaf_count.count_ones() as usize {
+        return false;
+    }
+
+    let mut index = proof.leaf_index - offset;
+    let peak = proof.siblings.iter().fold(leaf, |node, sibling| {
+        let parent = if index & 1 == 1 { hash_pair(sibling, &node) } else { hash_pair(&node, sibling) };
+        index >>= 1;
+        parent
+    });
+    proof.peaks[position] == peak && root(proof.leaf_count, &proof.peaks) == *root_hash
+}
+
+/// Peaks of the accumulator of a list of leaves
+#[cfg(feature = "std")]
+pub fn peaks(leaves: &[H256]) -> Vec<H256> {
+    let mut peaks = Vec::new();
+    for (count, leaf) in leaves.iter().enumerate() {
+        append(&mut peaks, count as u64, *leaf);
+    }
+    peaks
+}
+
+/// Generate the proof of a leaf of the accumulator of a list of leaves
+#[cfg(feature = "std")]
+pub fn generate_proof(leaves: &[H256], leaf_index: u64) -> Option<Proof> {
+    let leaf_count = leaves.len() as u64;
+    let (_, offset, height) = mountain_of(leaf_count, leaf_index)?;
+
+    let mut level = leaves[offset as usize..(offset + (1u64 << height)) as usize].to_vec();
+    let mut index = (leaf_index - offset) as usize;
+    let mut siblings = Vec::with_capacity(height as usize);
+    while level.len() > 1 {
+        siblings.push(level[index ^ 1]);
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+        index >>= 1;
+    }
+
+    Some(Proof { leaf_index, leaf_count, siblings, peaks: peaks(leaves) })
+}
@@ -0,0 +1,147 @@
+// Copyright 2018 Commonwealth Labs, Inc.
+// This file is part of Edgeware.
+
+// Edgeware is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Edgeware is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Edgeware.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Minimal Recursive Length Prefix encoding, enough to read Ethereum blocks,
+//! receipts and trie nodes.
+
+use rstd::prelude::*;
+
+/// A single decoded RLP item borrowing from the encoded data.
+#[derive(Clone, Copy)]
+pub struct Rlp<'a> {
+    raw: &'a [u8],
+    payload: &'a [u8],
+    is_list: bool,
+}
+
+impl<'a> Rlp<'a> {
+    /// Decode an item that spans the whole of `data`.
+    pub fn new(data: &'a [u8]) -> Result<Self, &'static str> {
+        let (item, rest) = Self::split(data)?;
+        if !rest.is_empty() {
+            return Err("Invalid trailing RLP data");
+        }
+        Ok(item)
+    }
+
+    /// Decode the first item of `data`, returning it along with the remaining data.
+    fn split(data: &'a [u8]) -> Result<(Self, &'a [u8]), &'static str> {
+        let first = *data.first().ok_or("Invalid empty RLP data")?;
+        let (offset, len, is_list) = match first {
+            0x00..=0x7f => (0, 1, false),
+            0x80..=0xb7 => (1, (first - 0x80) as usize, false),
+            0xb8..=0xbf => {
+                let len_of_len = (first - 0xb7) as usize;
+                (1 + len_of_len, Self::read_len(&data[1..], len_of_len)?, false)
+            },
+            0xc0..=0xf7 => (1, (first - 0xc0) as usize, true),
+            _ => {
+                let len_of_len = (first - 0xf7) as usize;
+                (1 + len_of_len, Self::read_len(&data[1..], len_of_len)?, true)
+            },
+        };
+
+        let end = offset.checked_add(len).ok_or("Invalid RLP length")?;
+        if end > data.len() {
+            return Err("Invalid RLP length");
+        }
+        let item = Rlp { raw: &data[..end], payload: &data[offset..end], is_list };
+        Ok((item, &data[end..]))
+    }
+
+    fn read_len(data: &[u8], len_of_len: usize) -> Result<usize, &'static str> {
+        if len_of_len > 4 || data.len() < len_of_len {
+            return Err("Invalid RLP length");
+        }
+        Ok(data[..len_of_len].iter().fold(0usize, |len, b| (len << 8) | *b as usize))
+    }
+
+    /// Whether the item is a list
+    pub fn is_list(&self) -> bool {
+        self.is_list
+    }
+
+    /// The full encoding of the item, including its prefix
+    pub fn as_raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// The bytes of a string item
+    pub fn data(&self) -> Result<&'a [u8], &'static str> {
+        if self.is_list {
+            return Err("Expected RLP string, found list");
+        }
+        Ok(self.payload)
+    }
+
+    /// The items of a list item
+    pub fn list(&self) -> Result<Vec<Rlp<'a>>, &'static str> {
+        if !self.is_list {
+            return Err("Expected RLP list, found string");
+        }
+        let mut items = Vec::new();
+        let mut rest = self.payload;
+        while !rest.is_empty() {
+            let (item, next) = Self::split(rest)?;
+            items.push(item);
+            rest = next;
+        }
+        Ok(items)
+    }
+}
+
+fn encode_prefix(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+    let len_bytes = encode_be(len as u64);
+    let mut out = vec![offset + 55 + len_bytes.len() as u8];
+    out.extend_from_slice(&len_bytes);
+    out
+}
+
+/// Big endian bytes of an integer without leading zeroes
+fn encode_be(value: u64) -> Vec<u8> {
+    let bytes = [
+        (value >> 56) as u8, (value >> 48) as u8, (value >> 40) as u8, (value >> 32) as u8,
+        (value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8,
+    ];
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    bytes[start..].to_vec()
+}
+
+/// RLP encode a byte string
+pub fn encode_bytes(data: &[u8]) -> Vec<u8> {
+    if data.len() == 1 && data[0] < 0x80 {
+        return data.to_vec();
+    }
+    let mut out = encode_prefix(data.len(), 0x80);
+    out.extend_from_slice(data);
+    out
+}
+
+/// RLP encode a list of already encoded items
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut out = encode_prefix(payload.len(), 0xc0);
+    out.extend_from_slice(&payload);
+    out
+}
+
+/// RLP encode an integer, as used for transaction and receipt trie keys
+pub fn encode_u64(value: u64) -> Vec<u8> {
+    encode_bytes(&encode_be(value))
+}